@@ -34,21 +34,11 @@ mod test {
 
     #[test]
     fn test_find_read_ids() -> eyre::Result<()> {
-        // let path = PathBuf::from("../../extra/multi_fast5_zip_v0.pod5");
-        // println!("{path:?}");
-        // run(path)?;
-
-        // let path = PathBuf::from("../../extra/multi_fast5_zip_v1.pod5");
-        // println!("{path:?}");
-        // run(path)?;
-
-        // let path = PathBuf::from("../../extra/multi_fast5_zip_v2.pod5");
-        // println!("{path:?}");
-        // run(path)?;
-
-        let path = PathBuf::from("../../extra/multi_fast5_zip_v3.pod5");
-        println!("{path:?}");
-        run(path)?;
+        for version in 0..4 {
+            let path = PathBuf::from(format!("../../extra/multi_fast5_zip_v{version}.pod5"));
+            println!("{path:?}");
+            run(path)?;
+        }
         Ok(())
     }
 }
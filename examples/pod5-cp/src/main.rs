@@ -23,8 +23,9 @@ fn main() {
     let mut reader = Reader::from_reader(input_file).unwrap();
     let mut writer = Writer::from_writer(output_file).unwrap();
 
+    // Copy the compressed signal as is instead of decoding and encoding it
     let mut guard = writer.guard::<SignalDataFrame>();
-    for df in reader.signal_dfs().unwrap() {
+    for df in reader.signal_dfs().unwrap().raw() {
         let df = df.unwrap();
         guard.write_batch(&df).unwrap();
     }
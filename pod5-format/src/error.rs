@@ -1,5 +1,7 @@
 use flatbuffers::InvalidFlatbuffer;
 
+use crate::footer::footer_generated::minknow::reads_format::ContentType;
+
 #[derive(thiserror::Error, Debug)]
 pub enum FormatError {
     #[error("Error encountered in Footer operation: {0}")]
@@ -21,6 +23,11 @@ pub enum FooterError {
     )]
     ContentsMissing,
 
+    /// The footer length stored at the end of the file doesn't fit within the
+    /// file
+    #[error("Invalid footer length: {0}")]
+    InvalidFooterLength(i64),
+
     /// Failed to find the Signal Table
     #[error("Missing Signal table from POD5")]
     SignalTableMissing,
@@ -32,4 +39,12 @@ pub enum FooterError {
     /// Failed to find the Run Info Table
     #[error("Missing Run Info table from POD5")]
     RunInfoTableMissing,
+
+    /// More than one table of a type that should only appear once
+    #[error("Multiple {0:?} tables in POD5 footer")]
+    DuplicateTable(ContentType),
+
+    /// The file wasn't written with a read_id index
+    #[error("Missing read_id index from POD5")]
+    ReadIdIndexMissing,
 }
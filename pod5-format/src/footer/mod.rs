@@ -2,7 +2,7 @@ use std::io::{self, Read, Seek, SeekFrom, Write};
 
 use flatbuffers::root;
 use footer_generated::minknow::reads_format::{
-    ContentType, EmbeddedFile, EmbeddedFileArgs, Footer, FooterArgs,
+    ContentType, EmbeddedFile, EmbeddedFileArgs, Footer, FooterArgs, Format,
 };
 
 use crate::{FILE_SIGNATURE, FormatError, error::FooterError};
@@ -13,10 +13,11 @@ pub mod footer_generated;
 pub const FOOTER_MAGIC: [u8; 8] = [b'F', b'O', b'O', b'T', b'E', b'R', 0x000, 0x000];
 
 /// Contains information about the location, size, and type of a POD5 Table
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct TableInfo {
     offset: i64,
     length: i64,
+    format: Format,
     content_type: ContentType,
 }
 
@@ -25,10 +26,20 @@ impl TableInfo {
         Self {
             offset,
             length,
+            format: Format::FeatherV2,
             content_type,
         }
     }
 
+    fn from_embedded_file(efile: &EmbeddedFile) -> Self {
+        Self {
+            offset: efile.offset(),
+            length: efile.length(),
+            format: efile.format(),
+            content_type: efile.content_type(),
+        }
+    }
+
     pub fn offset(&self) -> i64 {
         self.offset
     }
@@ -36,24 +47,42 @@ impl TableInfo {
     pub fn length(&self) -> i64 {
         self.length
     }
+
+    pub fn format(&self) -> Format {
+        self.format
+    }
+
+    pub fn content_type(&self) -> ContentType {
+        self.content_type
+    }
+
+    /// Get the bytes of the table from the full contents of a POD5 file
+    /// without copying, or `None` if the table is out of bounds.
+    pub fn slice<'a>(&self, data: &'a [u8]) -> Option<&'a [u8]> {
+        let start = usize::try_from(self.offset).ok()?;
+        let end = start.checked_add(usize::try_from(self.length).ok()?)?;
+        data.get(start..end)
+    }
 }
 
 #[derive(Debug)]
 pub struct RunInfoTable(TableInfo);
 
-impl RunInfoTable {
-    pub fn as_ref(&self) -> &TableInfo {
+impl AsRef<TableInfo> for RunInfoTable {
+    fn as_ref(&self) -> &TableInfo {
         &self.0
     }
 }
 
 #[derive(Debug)]
 pub struct ReadTable(TableInfo);
-impl ReadTable {
-    pub fn as_ref(&self) -> &TableInfo {
+impl AsRef<TableInfo> for ReadTable {
+    fn as_ref(&self) -> &TableInfo {
         &self.0
     }
+}
 
+impl ReadTable {
     pub fn read_to_buf<R: Read + Seek>(
         &self,
         reader: &mut R,
@@ -92,6 +121,16 @@ impl AsRef<TableInfo> for SignalTable {
     }
 }
 
+/// Optional index from read_id to row of the Read table.
+#[derive(Debug)]
+pub struct ReadIdIndexTable(TableInfo);
+
+impl AsRef<TableInfo> for ReadIdIndexTable {
+    fn as_ref(&self) -> &TableInfo {
+        &self.0
+    }
+}
+
 pub struct ParsedFooter {
     data: Vec<u8>,
 }
@@ -113,6 +152,13 @@ impl ParsedFooter {
             .read_exact(&mut buf)
             .map_err(FooterError::FooterIOError)?;
         let flen = i64::from_le_bytes(buf);
+        let footer_start = reader
+            .stream_position()
+            .map_err(FooterError::FooterIOError)?
+            - 8;
+        if flen < 0 || flen as u64 > footer_start {
+            return Err(FooterError::InvalidFooterLength(flen).into());
+        }
         reader
             .seek(SeekFrom::End(footer_length - flen))
             .map_err(FooterError::FooterIOError)?;
@@ -123,33 +169,73 @@ impl ParsedFooter {
         Ok(Self { data: buf })
     }
 
+    /// Parse a POD5 Flatbuffer footer from the full contents of a POD5 file,
+    /// for example a memory mapped file.
+    pub fn from_bytes(data: &[u8]) -> Result<Self, FormatError> {
+        let footer_length_end = data
+            .len()
+            .checked_sub(FILE_SIGNATURE.len() + 16)
+            .ok_or(FooterError::InvalidFooterLength(data.len() as i64))?;
+        let footer_length_start = footer_length_end
+            .checked_sub(8)
+            .ok_or(FooterError::InvalidFooterLength(data.len() as i64))?;
+        let flen = i64::from_le_bytes(
+            data[footer_length_start..footer_length_end]
+                .try_into()
+                .expect("slice is 8 bytes"),
+        );
+        let footer_start = usize::try_from(flen)
+            .ok()
+            .and_then(|flen| footer_length_start.checked_sub(flen))
+            .ok_or(FooterError::InvalidFooterLength(flen))?;
+        Ok(Self {
+            data: data[footer_start..footer_length_start].to_vec(),
+        })
+    }
+
     pub fn footer(&self) -> Result<Footer<'_>, FooterError> {
         Ok(root::<Footer>(&self.data)?)
     }
 
+    /// Find the table with the given content type, failing if there isn't
+    /// exactly one.
     fn find_table(
         &self,
         content_type: ContentType,
         err: FooterError,
     ) -> Result<TableInfo, FormatError> {
-        let footer = self.footer()?;
-        let contents = footer.contents().ok_or(FooterError::ContentsMissing)?;
-        let mut efile = None;
-        for c in contents {
-            if c.content_type() == content_type {
-                efile = Some(c);
-                break;
-            }
+        let mut tables = self
+            .tables()?
+            .into_iter()
+            .filter(|t| t.content_type == content_type);
+        let table = tables.next().ok_or(err)?;
+        if tables.next().is_some() {
+            return Err(FooterError::DuplicateTable(content_type).into());
         }
-        let efile = efile.ok_or(err)?;
+        Ok(table)
+    }
 
-        Ok(TableInfo {
-            offset: efile.offset(),
-            length: efile.length(),
-            content_type: content_type,
+    /// Owned view of every field in the footer.
+    pub fn info(&self) -> Result<FooterInfo, FormatError> {
+        let footer = self.footer()?;
+        Ok(FooterInfo {
+            file_identifier: footer.file_identifier().map(String::from),
+            software: footer.software().map(String::from),
+            pod5_version: footer.pod5_version().map(String::from),
+            tables: self.tables()?,
         })
     }
 
+    /// Every embedded file listed in the footer, in footer order.
+    pub fn tables(&self) -> Result<Vec<TableInfo>, FormatError> {
+        let footer = self.footer()?;
+        let contents = footer.contents().ok_or(FooterError::ContentsMissing)?;
+        Ok(contents
+            .iter()
+            .map(|efile| TableInfo::from_embedded_file(&efile))
+            .collect())
+    }
+
     pub fn read_table(&self) -> Result<ReadTable, FormatError> {
         Ok(ReadTable(self.find_table(
             ContentType::ReadsTable,
@@ -170,6 +256,71 @@ impl ParsedFooter {
             FooterError::RunInfoTableMissing,
         )?))
     }
+
+    /// Location of the read_id index, if the file was written with one.
+    pub fn read_id_index_table(&self) -> Result<ReadIdIndexTable, FormatError> {
+        Ok(ReadIdIndexTable(self.find_table(
+            ContentType::ReadIdIndex,
+            FooterError::ReadIdIndexMissing,
+        )?))
+    }
+}
+
+/// Typed view of a POD5 footer, see [`ParsedFooter::info`].
+#[derive(Debug, Clone)]
+pub struct FooterInfo {
+    file_identifier: Option<String>,
+    software: Option<String>,
+    pod5_version: Option<String>,
+    tables: Vec<TableInfo>,
+}
+
+impl FooterInfo {
+    /// UUID identifying the file, also stored in each table's schema metadata.
+    pub fn file_identifier(&self) -> Option<&str> {
+        self.file_identifier.as_deref()
+    }
+
+    /// Name of the software that wrote the file.
+    pub fn software(&self) -> Option<&str> {
+        self.software.as_deref()
+    }
+
+    /// Version of the POD5 specification the file was written with.
+    pub fn pod5_version(&self) -> Option<&str> {
+        self.pod5_version.as_deref()
+    }
+
+    /// Every embedded file, in footer order.
+    pub fn tables(&self) -> &[TableInfo] {
+        &self.tables
+    }
+
+    /// Embedded files with the given content type.
+    pub fn tables_of(&self, content_type: ContentType) -> impl Iterator<Item = &TableInfo> {
+        self.tables
+            .iter()
+            .filter(move |t| t.content_type == content_type)
+    }
+
+    /// Content types other than `OtherIndex` that appear more than once.
+    /// Readers can't tell which of the tables is the real one, so these files
+    /// are rejected when looking up the table.
+    pub fn duplicate_tables(&self) -> Vec<ContentType> {
+        let mut duplicates = Vec::new();
+        for (i, table) in self.tables.iter().enumerate() {
+            let content_type = table.content_type;
+            if content_type != ContentType::OtherIndex
+                && !duplicates.contains(&content_type)
+                && self.tables[..i]
+                    .iter()
+                    .any(|t| t.content_type == content_type)
+            {
+                duplicates.push(content_type);
+            }
+        }
+        duplicates
+    }
 }
 
 /// Build a new POD5 FlatBuffer's footer, useful for writing new POD5 files.
@@ -195,10 +346,10 @@ impl FooterBuilder {
         let mut etables = Vec::with_capacity(tables.len());
         for table in tables {
             let efile_args = EmbeddedFileArgs {
-                offset: table.offset as i64,
-                length: table.length as i64,
+                offset: table.offset,
+                length: table.length,
+                format: table.format,
                 content_type: table.content_type,
-                ..Default::default()
             };
             let efile = EmbeddedFile::create(&mut builder, &efile_args);
             etables.push(efile);
@@ -278,6 +429,82 @@ mod test {
         assert!(footer.read_table().is_ok());
         assert!(footer.run_info_table().is_ok());
         assert!(footer.signal_table().is_ok());
+        assert!(matches!(
+            footer.read_id_index_table(),
+            Err(FormatError::FooterError(FooterError::ReadIdIndexMissing))
+        ));
+
+        let tables = footer.tables()?;
+        assert_eq!(tables.len(), 3);
+        let signal_table = footer.signal_table()?;
+        let signal = tables
+            .iter()
+            .find(|t| t.content_type() == ContentType::SignalTable)
+            .unwrap();
+        assert_eq!(signal.offset(), signal_table.as_ref().offset());
+        Ok(())
+    }
+
+    #[test]
+    fn test_footer_info() -> eyre::Result<()> {
+        let path = "../extra/multi_fast5_zip_v3.pod5";
+        let footer = ParsedFooter::read_footer(File::open(path)?)?;
+        let info = footer.info()?;
+        let raw = footer.footer()?;
+        assert_eq!(info.file_identifier(), raw.file_identifier());
+        assert_eq!(info.software(), raw.software());
+        assert_eq!(info.pod5_version(), raw.pod5_version());
+        assert_eq!(info.tables().len(), 3);
+        assert!(
+            info.tables()
+                .iter()
+                .all(|t| t.format() == Format::FeatherV2)
+        );
+        assert!(info.duplicate_tables().is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_footer_duplicate_tables() -> eyre::Result<()> {
+        let tables = [
+            TableInfo::new(24, 8, ContentType::ReadsTable),
+            TableInfo::new(48, 8, ContentType::OtherIndex),
+            TableInfo::new(72, 8, ContentType::ReadsTable),
+            TableInfo::new(96, 8, ContentType::OtherIndex),
+            TableInfo::new(120, 8, ContentType::SignalTable),
+        ];
+        let builder = FooterBuilder::new("id".into(), "software".into(), "0.0.0".into());
+        let footer = ParsedFooter {
+            data: builder.build_footer(&tables),
+        };
+        let info = footer.info()?;
+        assert_eq!(info.file_identifier(), Some("id"));
+        assert_eq!(info.tables_of(ContentType::ReadsTable).count(), 2);
+        assert_eq!(info.duplicate_tables(), [ContentType::ReadsTable]);
+        assert!(matches!(
+            footer.read_table(),
+            Err(FormatError::FooterError(FooterError::DuplicateTable(
+                ContentType::ReadsTable
+            )))
+        ));
+        assert_eq!(footer.signal_table()?.as_ref().offset(), 120);
+        Ok(())
+    }
+
+    #[test]
+    fn test_footer_from_bytes() -> eyre::Result<()> {
+        let path = "../extra/multi_fast5_zip_v3.pod5";
+        let data = std::fs::read(path)?;
+        let footer = ParsedFooter::from_bytes(&data)?;
+        let expected = ParsedFooter::read_footer(File::open(path)?)?;
+        assert_eq!(footer.data, expected.data);
+
+        let signal_table = footer.signal_table()?;
+        let table = signal_table.as_ref().slice(&data).unwrap();
+        assert_eq!(table.len() as i64, signal_table.as_ref().length());
+        assert_eq!(&table[..6], b"ARROW1");
+
+        assert!(ParsedFooter::from_bytes(&data[..16]).is_err());
         Ok(())
     }
 }
@@ -1,15 +1,18 @@
 mod error;
 pub mod footer;
+pub mod validate;
 
 use std::io::{Read, Seek};
 
-pub use error::FormatError;
+pub use error::{FooterError, FormatError};
 pub use footer::ParsedFooter;
 pub use footer::FooterBuilder;
 pub use footer::FOOTER_MAGIC;
+pub use footer::FooterInfo;
 pub use footer::TableInfo;
 
 pub use footer::footer_generated;
+pub use validate::validate;
 
 pub const FILE_SIGNATURE: [u8; 8] = [0x8b, b'P', b'O', b'D', b'\r', b'\n', 0x1a, b'\n'];
 
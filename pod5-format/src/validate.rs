@@ -0,0 +1,334 @@
+//! Structural validation of POD5 files.
+//!
+//! [`validate`] checks the layout of a POD5 file without decoding any of the
+//! tables, collecting every problem it finds into a [`ValidationReport`]
+//! instead of stopping at the first one.
+use std::io::{self, Read, Seek, SeekFrom};
+
+use polars_arrow_format::ipc::{FooterRef, KeyValueRef, planus::ReadAsRoot};
+
+use crate::{
+    FILE_SIGNATURE, ParsedFooter,
+    footer::footer_generated::minknow::reads_format::{ContentType, Format},
+};
+
+const SECTION_MARKER_LEN: u64 = 16;
+const ARROW_MAGIC: [u8; 6] = *b"ARROW1";
+/// Leading magic padded to 8 bytes, plus the trailing footer length and magic
+const ARROW_MIN_LEN: u64 = 8 + 4 + ARROW_MAGIC.len() as u64;
+const FILE_IDENTIFIER_KEY: &str = "MINKNOW:file_identifier";
+
+/// A single problem found while validating a POD5 file.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum Problem {
+    #[error("File is too short to be a POD5 file: {0} bytes")]
+    TooShort(u64),
+
+    #[error("Invalid signature at the start of the file")]
+    StartSignature,
+
+    #[error("Invalid signature at the end of the file")]
+    EndSignature,
+
+    /// The section marker at this offset doesn't match the one following the
+    /// starting signature.
+    #[error("Section marker at offset {0} doesn't match the file's section marker")]
+    SectionMarker(u64),
+
+    #[error("Unable to parse footer: {0}")]
+    Footer(String),
+
+    /// A table type that should only appear once is listed multiple times.
+    #[error("Multiple {0:?} tables in footer")]
+    DuplicateTable(ContentType),
+
+    #[error(
+        "Table {index} ({content_type:?}) at offset {offset} with length {length} is outside of the {file_len} byte file"
+    )]
+    TableOutOfBounds {
+        index: usize,
+        content_type: ContentType,
+        offset: i64,
+        length: i64,
+        file_len: u64,
+    },
+
+    #[error("Table {index} ({content_type:?}) offset {offset} isn't 8-byte aligned")]
+    TableMisaligned {
+        index: usize,
+        content_type: ContentType,
+        offset: i64,
+    },
+
+    #[error("Table {index} ({content_type:?}) has unsupported format {format:?}")]
+    UnsupportedFormat {
+        index: usize,
+        content_type: ContentType,
+        format: Format,
+    },
+
+    #[error("Table {index} ({content_type:?}) isn't a valid Arrow IPC file: {reason}")]
+    InvalidArrow {
+        index: usize,
+        content_type: ContentType,
+        reason: String,
+    },
+
+    /// The table's `MINKNOW:file_identifier` schema metadata doesn't match
+    /// the footer.
+    #[error(
+        "Table {index} ({content_type:?}) file_identifier {found:?} doesn't match footer {expected:?}"
+    )]
+    FileIdentifierMismatch {
+        index: usize,
+        content_type: ContentType,
+        expected: Option<String>,
+        found: Option<String>,
+    },
+}
+
+/// Every problem found by [`validate`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ValidationReport {
+    problems: Vec<Problem>,
+}
+
+impl ValidationReport {
+    /// Whether no problems were found.
+    pub fn is_valid(&self) -> bool {
+        self.problems.is_empty()
+    }
+
+    pub fn problems(&self) -> &[Problem] {
+        &self.problems
+    }
+
+    pub fn into_problems(self) -> Vec<Problem> {
+        self.problems
+    }
+}
+
+/// Check the structure of a POD5 file.
+///
+/// This checks:
+/// - the signatures at the start and end of the file
+/// - the section marker before and after every table, and before the
+///   ending signature
+/// - that each table listed in the footer is within the file and 8-byte
+///   aligned, and that tables which should be unique aren't duplicated
+/// - that each table is an Arrow IPC (FeatherV2) file, only reading its
+///   header and footer
+/// - that each table's `MINKNOW:file_identifier` schema metadata matches the
+///   footer
+///
+/// I/O errors are returned as errors, while problems with the contents of the
+/// file are collected into the report.
+pub fn validate<R: Read + Seek>(mut reader: R) -> io::Result<ValidationReport> {
+    let mut problems = Vec::new();
+    let file_len = reader.seek(SeekFrom::End(0))?;
+    let min_len = 2 * (FILE_SIGNATURE.len() as u64 + SECTION_MARKER_LEN);
+    if file_len < min_len {
+        problems.push(Problem::TooShort(file_len));
+        return Ok(ValidationReport { problems });
+    }
+
+    if read_at::<8, _>(&mut reader, 0)? != FILE_SIGNATURE {
+        problems.push(Problem::StartSignature);
+    }
+    if read_at::<8, _>(&mut reader, file_len - 8)? != FILE_SIGNATURE {
+        problems.push(Problem::EndSignature);
+    }
+    let section_marker = read_at::<16, _>(&mut reader, 8)?;
+    // Tables share the marker between them, so collect the offsets first to
+    // only check and report each one once.
+    let mut marker_offsets = vec![file_len - 24];
+
+    let footer = match ParsedFooter::read_footer(&mut reader).and_then(|f| f.info()) {
+        Ok(footer) => footer,
+        Err(e) => {
+            problems.push(Problem::Footer(e.to_string()));
+            return Ok(ValidationReport { problems });
+        }
+    };
+
+    let mut table_problems = Vec::new();
+    for (index, table) in footer.tables().iter().enumerate() {
+        let content_type = table.content_type();
+        let (offset, length) = (table.offset(), table.length());
+        let end = offset.checked_add(length);
+        if offset < SECTION_MARKER_LEN as i64
+            || length < 0
+            || end.is_none_or(|end| end as u64 > file_len)
+        {
+            table_problems.push(Problem::TableOutOfBounds {
+                index,
+                content_type,
+                offset,
+                length,
+                file_len,
+            });
+            continue;
+        }
+        let (offset, length) = (offset as u64, length as u64);
+        if offset % 8 != 0 {
+            table_problems.push(Problem::TableMisaligned {
+                index,
+                content_type,
+                offset: offset as i64,
+            });
+        }
+        marker_offsets.push(offset - SECTION_MARKER_LEN);
+        marker_offsets.push((offset + length).next_multiple_of(8));
+
+        if table.format() != Format::FeatherV2 {
+            table_problems.push(Problem::UnsupportedFormat {
+                index,
+                content_type,
+                format: table.format(),
+            });
+            continue;
+        }
+        match arrow_file_identifier(&mut reader, offset, length)? {
+            Ok(found) if found.as_deref() == footer.file_identifier() => (),
+            Ok(found) => table_problems.push(Problem::FileIdentifierMismatch {
+                index,
+                content_type,
+                expected: footer.file_identifier().map(String::from),
+                found,
+            }),
+            Err(reason) => table_problems.push(Problem::InvalidArrow {
+                index,
+                content_type,
+                reason,
+            }),
+        }
+    }
+
+    problems.extend(
+        footer
+            .duplicate_tables()
+            .into_iter()
+            .map(Problem::DuplicateTable),
+    );
+    marker_offsets.sort_unstable();
+    marker_offsets.dedup();
+    for offset in marker_offsets {
+        if offset + SECTION_MARKER_LEN > file_len
+            || read_at::<16, _>(&mut reader, offset)? != section_marker
+        {
+            problems.push(Problem::SectionMarker(offset));
+        }
+    }
+    problems.extend(table_problems);
+    Ok(ValidationReport { problems })
+}
+
+fn read_at<const N: usize, R: Read + Seek>(reader: &mut R, offset: u64) -> io::Result<[u8; N]> {
+    let mut buf = [0u8; N];
+    reader.seek(SeekFrom::Start(offset))?;
+    reader.read_exact(&mut buf)?;
+    Ok(buf)
+}
+
+/// Check the Arrow IPC header and footer of the table at `offset..offset +
+/// length`, returning the `MINKNOW:file_identifier` from its schema metadata,
+/// or the reason the table isn't valid.
+fn arrow_file_identifier<R: Read + Seek>(
+    reader: &mut R,
+    offset: u64,
+    length: u64,
+) -> io::Result<Result<Option<String>, String>> {
+    if length < ARROW_MIN_LEN {
+        return Ok(Err(format!("too short, {length} bytes")));
+    }
+    if read_at::<6, _>(reader, offset)? != ARROW_MAGIC {
+        return Ok(Err("missing leading ARROW1 magic".to_string()));
+    }
+    let tail = read_at::<10, _>(reader, offset + length - 10)?;
+    if tail[4..] != ARROW_MAGIC {
+        return Ok(Err("missing trailing ARROW1 magic".to_string()));
+    }
+    let footer_len = i32::from_le_bytes(tail[..4].try_into().expect("4 bytes"));
+    if footer_len < 0 || footer_len as u64 > length - ARROW_MIN_LEN {
+        return Ok(Err(format!("invalid footer length {footer_len}")));
+    }
+    let mut footer = vec![0u8; footer_len as usize];
+    reader.seek(SeekFrom::Start(offset + length - 10 - footer_len as u64))?;
+    reader.read_exact(&mut footer)?;
+    Ok(parse_arrow_footer(&footer, length))
+}
+
+fn parse_arrow_footer(footer: &[u8], length: u64) -> Result<Option<String>, String> {
+    let footer = FooterRef::read_as_root(footer).map_err(|e| e.to_string())?;
+    let blocks = footer.record_batches().map_err(|e| e.to_string())?;
+    for block in blocks.into_iter().flatten() {
+        let end =
+            block.offset() as i128 + block.meta_data_length() as i128 + block.body_length() as i128;
+        if block.offset() < 0 || end > length as i128 {
+            return Err(format!(
+                "record batch at {} is out of bounds",
+                block.offset()
+            ));
+        }
+    }
+    let schema = footer
+        .schema()
+        .map_err(|e| e.to_string())?
+        .ok_or("missing schema")?;
+    let metadata = schema.custom_metadata().map_err(|e| e.to_string())?;
+    for kv in metadata.into_iter().flatten() {
+        let kv: KeyValueRef = kv.map_err(|e| e.to_string())?;
+        if kv.key().map_err(|e| e.to_string())? == Some(FILE_IDENTIFIER_KEY) {
+            return Ok(kv.value().map_err(|e| e.to_string())?.map(String::from));
+        }
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod test {
+    use std::{fs::File, io::Cursor};
+
+    use super::*;
+
+    #[test]
+    fn test_validate() -> eyre::Result<()> {
+        for version in 0..4 {
+            let path = format!("../extra/multi_fast5_zip_v{version}.pod5");
+            let report = validate(File::open(&path)?)?;
+            assert!(report.is_valid(), "{path}: {:?}", report.problems());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_validate_problems() -> eyre::Result<()> {
+        let mut data = std::fs::read("../extra/multi_fast5_zip_v3.pod5")?;
+        let footer = ParsedFooter::from_bytes(&data)?.info()?;
+        let first = &footer.tables()[0];
+        let second = footer.tables()[1].offset() as usize;
+
+        data[0] = 0;
+        // Section marker between the first and second table
+        data[second - 1] ^= 0xff;
+        // Arrow magic of the first table
+        data[first.offset() as usize] = 0;
+        let report = validate(Cursor::new(&data))?;
+        assert_eq!(
+            report.problems(),
+            [
+                Problem::StartSignature,
+                Problem::SectionMarker(second as u64 - 16),
+                Problem::InvalidArrow {
+                    index: 0,
+                    content_type: first.content_type(),
+                    reason: "missing leading ARROW1 magic".to_string(),
+                },
+            ]
+        );
+
+        let report = validate(Cursor::new(&data[..48]))?;
+        assert!(matches!(report.problems().last(), Some(Problem::Footer(_))));
+        Ok(())
+    }
+}
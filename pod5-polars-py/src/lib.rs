@@ -11,7 +11,7 @@ use svb16::{decode, encode};
 
 /// An iterator over the SignalTable, yielding polars DataFrames
 #[pyclass]
-struct SignalIter(pod5_polars_rs::dataframe::SignalDataFrameIter);
+struct SignalIter(pod5_polars_rs::dataframe::SignalDataFrameIter<File>);
 
 #[pymethods]
 impl SignalIter {
@@ -28,7 +28,7 @@ impl SignalIter {
 }
 
 #[pyclass]
-struct RunInfoIter(pod5_polars_rs::dataframe::RunInfoDataFrameIter);
+struct RunInfoIter(pod5_polars_rs::dataframe::RunInfoDataFrameIter<File>);
 
 #[pymethods]
 impl RunInfoIter {
@@ -45,7 +45,7 @@ impl RunInfoIter {
 }
 
 #[pyclass]
-struct ReadIter(pod5_polars_rs::dataframe::ReadDataFrameIter);
+struct ReadIter(pod5_polars_rs::dataframe::ReadDataFrameIter<File>);
 
 #[pymethods]
 impl ReadIter {
@@ -8,10 +8,10 @@ use polars::{
 };
 use polars_arrow::{
     array::{
-        Array, BinaryArray, BinaryViewArray, DictionaryArray, FixedSizeBinaryArray,
-        Int16Array, ListArray, MapArray, MutableArray, MutableBinaryArray,
-        MutableFixedSizeBinaryArray, MutableListArray, MutablePrimitiveArray, MutableUtf8Array,
-        PrimitiveArray, StructArray, TryPush, Utf8Array, Utf8ViewArray,
+        Array, BinaryArray, BinaryViewArray, DictionaryArray, FixedSizeBinaryArray, Int16Array,
+        ListArray, MapArray, MutableArray, MutableBinaryArray, MutableFixedSizeBinaryArray,
+        MutableListArray, MutablePrimitiveArray, MutableUtf8Array, PrimitiveArray, StructArray,
+        TryPush, Utf8Array, Utf8ViewArray,
     },
     datatypes::{ExtensionType, IntegerType},
     offset::OffsetsBuffer,
@@ -21,7 +21,7 @@ use polars_schema::Schema;
 use svb16;
 use uuid::Uuid;
 
-use crate::dataframe::schema::map_field;
+use crate::{dataframe::schema::map_field, error::Pod5Error};
 
 /// Convert Arrow arrays into polars Series. This works for almost all arrays
 /// except the Extensions. In order for properly handle Extension types, the
@@ -40,12 +40,19 @@ use crate::dataframe::schema::map_field;
 /// Extension(minknow.vbz) => downcast_ref into LargeBinaryArray => into_inner
 /// and split into components (offsets, bitmap, etc.) => LargeBinary::new with
 /// components => boxed to Box<dyn Array> => Series::try_from works properly
-pub(crate) fn array_to_series(field: &pl::ArrowField, arr: Box<dyn Array>) -> Series {
+pub(crate) fn array_to_series(
+    field: &pl::ArrowField,
+    arr: Box<dyn Array>,
+) -> Result<Series, Pod5Error> {
     log::debug!("array_to_series: {field:?}");
     match Series::try_from((field, arr.clone())) {
-        Ok(series) => return series,
+        Ok(series) => return Ok(series),
         Err(e) => log::debug!("{e:?}, attempting conversion"),
     }
+    let unsupported = || Pod5Error::UnsupportedDataType {
+        column: field.name.to_string(),
+        dtype: format!("{:?}", field.dtype()),
+    };
 
     match field.dtype() {
         // Read UUIDs
@@ -56,18 +63,20 @@ pub(crate) fn array_to_series(field: &pl::ArrowField, arr: Box<dyn Array>) -> Se
             let arr = arr
                 .as_any()
                 .downcast_ref::<FixedSizeBinaryArray>()
-                .unwrap()
+                .ok_or_else(unsupported)?
                 .values_iter()
-                .map(|x| Some(Uuid::from_slice(x).unwrap().to_string()))
-                .collect::<Vec<_>>();
+                .enumerate()
+                .map(|(row, x)| {
+                    Uuid::from_slice(x)
+                        .map(|uuid| Some(uuid.to_string()))
+                        .map_err(|e| Pod5Error::InvalidReadId {
+                            row,
+                            reason: e.to_string(),
+                        })
+                })
+                .collect::<Result<Vec<_>, _>>()?;
             let arr = Utf8Array::<i32>::from(arr);
-
-            match Series::try_from((&field, arr.boxed())) {
-                Ok(series) => series,
-                Err(e) => {
-                    panic!("{e:?}");
-                }
-            }
+            Ok(Series::try_from((&field, arr.boxed()))?)
         }
 
         // Signal data
@@ -79,22 +88,14 @@ pub(crate) fn array_to_series(field: &pl::ArrowField, arr: Box<dyn Array>) -> Se
             let (_, offsets, values, bitmap) = arr
                 .as_any()
                 .downcast_ref::<LargeBinaryArray>()
-                .unwrap()
+                .ok_or_else(unsupported)?
                 .clone()
                 .into_inner();
             let conc =
                 LargeBinaryArray::new(pl::ArrowDataType::LargeBinary, offsets, values, bitmap);
-
-            match Series::try_from((&field, conc.boxed())) {
-                Ok(series) => series,
-                Err(e) => {
-                    panic!("{e:?}");
-                }
-            }
-        }
-        _ => {
-            panic!("unimplemented datatype: {:?}", field);
+            Ok(Series::try_from((&field, conc.boxed()))?)
         }
+        _ => Err(unsupported()),
     }
 }
 
@@ -321,7 +322,7 @@ pub(crate) fn record_batch_to_compat(
 
     let (schema, chunks) = batch.into_schema_and_arrays();
 
-    for ((name, field), array) in schema.iter().zip(chunks.into_iter()) {
+    for ((name, field), array) in schema.iter().zip(chunks) {
         log::debug!("record_btach_to_compat, field {field:?}");
         let farr = match (name.as_str(), &field.dtype) {
             // Signal in the ReadTable (list(u64))
@@ -371,7 +372,7 @@ fn convert_map_struct_arr(struct_arr: StructArray) -> StructArray {
 
     let fields = vec![
         ArrowField::new("key".into(), ArrowDataType::Utf8, false),
-        ArrowField::new("value".into(), ArrowDataType::Utf8, false),
+        ArrowField::new("value".into(), ArrowDataType::Utf8, true),
     ];
 
     let new_dt = ArrowDataType::Struct(fields);
@@ -413,11 +414,12 @@ fn large_list_to_map(
     let list_arr = list_arr.as_any().downcast_ref::<ListArray<i64>>().unwrap();
     let offsets = OffsetsBuffer::try_from(list_arr.offsets()).unwrap();
     let validity = list_arr.validity();
-    let chunk = list_arr.into_iter().next().unwrap().unwrap();
+    // Entries of every row, the offsets pointing into them
+    let chunk = list_arr.values();
 
     let fields = vec![
         ArrowField::new("key".into(), ArrowDataType::Utf8, false),
-        ArrowField::new("value".into(), ArrowDataType::Utf8, false),
+        ArrowField::new("value".into(), ArrowDataType::Utf8, true),
     ];
     let dt = ArrowDataType::Map(
         Box::new(ArrowField {
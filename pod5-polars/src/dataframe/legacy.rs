@@ -0,0 +1,759 @@
+//! Reading tables written with older versions of the POD5 specification.
+//!
+//! Files written before v3 of the table schemas have no Run Info table.
+//! Instead, the Read table stores the pore, calibration, end reason and run
+//! info of each read as dictionaries of structs. Some Read table columns were
+//! also added over time:
+//! - v0 has none of the columns from `num_minknow_events` to
+//!   `time_since_mux_change`
+//! - v0 and v1 don't have `num_samples`
+//!
+//! [`LegacyReads`] converts record batches of these tables into the layout of
+//! [`ReadSchema`] and [`RunInfoSchema`](super::schema::run_info_schema::RunInfoSchema),
+//! filling in the missing columns the same way the official `pod5 update`
+//! migrations do. [`to_legacy_batch`] goes the other way, for writing files
+//! that older tools can open.
+use std::{collections::HashMap, sync::Arc};
+
+use polars::{
+    frame::{DataFrame, group_by::GroupsIndicator},
+    prelude::{self as pl, CompatLevel, IdxCa, IdxSize, NamedFrom, PlSmallStr},
+    series::Series,
+};
+use polars_arrow::{
+    array::{Array, DictionaryArray, PrimitiveArray, StructArray},
+    datatypes::{ArrowSchema, Field, IntegerType},
+    io::ipc::IpcField,
+    record_batch::RecordBatchT,
+};
+
+use super::{
+    compatibility::{array_to_series, record_batch_to_compat},
+    schema::reads_schema::ReadSchema,
+};
+use crate::{
+    error::Pod5Error,
+    writer::{SchemaVersion, WriteError},
+};
+
+/// Dictionary columns of the old Read table, with the struct fields to take
+/// from each and the current column they become.
+const DICTIONARY_COLUMNS: [(&str, &[(&str, &str)]); 4] = [
+    (
+        "pore",
+        &[
+            ("channel", "channel"),
+            ("well", "well"),
+            ("pore_type", "pore_type"),
+        ],
+    ),
+    (
+        "calibration",
+        &[
+            ("offset", "calibration_offset"),
+            ("scale", "calibration_scale"),
+        ],
+    ),
+    (
+        "end_reason",
+        &[("name", "end_reason"), ("forced", "end_reason_forced")],
+    ),
+    ("run_info", &[("acquisition_id", "run_info")]),
+];
+
+/// Columns added to the Read table in v1, which are filled with these
+/// defaults when missing.
+const V1_DEFAULTS: [(&str, f32); 7] = [
+    ("num_minknow_events", 0.0),
+    ("tracked_scaling_scale", f32::NAN),
+    ("tracked_scaling_shift", f32::NAN),
+    ("predicted_scaling_scale", f32::NAN),
+    ("predicted_scaling_shift", f32::NAN),
+    ("num_reads_since_mux_change", 0.0),
+    ("time_since_mux_change", 0.0),
+];
+
+/// Converts Read table record batches from files older than v3 into the
+/// current layout.
+#[derive(Debug, Clone)]
+pub(crate) struct LegacyReads {
+    /// Fields of the old Read table
+    fields: Vec<Field>,
+    /// Samples in each Signal table row, used to fill in `num_samples` for
+    /// files written before it was added.
+    signal_samples: Option<Arc<Vec<u32>>>,
+    /// Current columns requested when reading a projection of the table
+    columns: Option<Vec<PlSmallStr>>,
+}
+
+impl LegacyReads {
+    /// Whether the Read table with these fields needs converting.
+    pub(crate) fn is_legacy(fields: &[Field]) -> bool {
+        DICTIONARY_COLUMNS.iter().any(|(name, _)| {
+            fields
+                .iter()
+                .any(|f| f.name == *name && is_struct_dictionary(f))
+        }) || Self::needs_signal_samples(fields)
+    }
+
+    /// Whether the Signal table samples are needed to fill in `num_samples`.
+    pub(crate) fn needs_signal_samples(fields: &[Field]) -> bool {
+        !fields.iter().any(|f| f.name == "num_samples")
+    }
+
+    pub(crate) fn new(fields: Vec<Field>, signal_samples: Option<Vec<u32>>) -> Self {
+        Self {
+            fields,
+            signal_samples: signal_samples.map(Arc::new),
+            columns: None,
+        }
+    }
+
+    /// Only convert the given current columns, see
+    /// [`LegacyReads::source_columns`].
+    pub(crate) fn with_columns(mut self, columns: &[PlSmallStr]) -> Self {
+        self.columns = Some(columns.to_vec());
+        self
+    }
+
+    /// Old Read table columns needed to get the given current columns.
+    pub(crate) fn source_columns(&self, columns: &[PlSmallStr]) -> Vec<PlSmallStr> {
+        let mut sources = Vec::with_capacity(columns.len());
+        for column in columns {
+            let source = DICTIONARY_COLUMNS
+                .iter()
+                .find(|(_, children)| children.iter().any(|(_, name)| name == column))
+                .map_or(column.as_str(), |(source, _)| source);
+            let source = match source {
+                "num_samples" if self.signal_samples.is_some() => "signal",
+                source => source,
+            };
+            if self.fields.iter().any(|f| f.name == source) {
+                sources.push(source.into());
+            }
+        }
+        sources
+    }
+
+    /// Convert a record batch of the old Read table into the current layout.
+    ///
+    /// Columns missing from older versions are filled in, unless they weren't
+    /// requested with [`LegacyReads::with_columns`].
+    pub(crate) fn to_df(
+        &self,
+        fields: &[Field],
+        batch: RecordBatchT<Box<dyn Array>>,
+    ) -> Result<DataFrame, Pod5Error> {
+        let height = batch.height();
+        let mut columns = HashMap::new();
+        for (arr, field) in batch.into_arrays().into_iter().zip(fields) {
+            match DICTIONARY_COLUMNS
+                .iter()
+                .find(|(name, _)| *name == field.name && is_struct_dictionary(field))
+            {
+                Some((_, children)) => {
+                    for series in expand_dictionary(field, arr, children)? {
+                        columns.insert(series.name().clone(), series);
+                    }
+                }
+                None => {
+                    let series = array_to_series(field, arr)?;
+                    columns.insert(field.name.clone(), series);
+                }
+            }
+        }
+        if !columns.contains_key("num_samples")
+            && let (Some(samples), Some(signal)) = (&self.signal_samples, columns.get("signal"))
+        {
+            let num_samples = num_samples(signal, samples)?;
+            columns.insert(num_samples.name().clone(), num_samples);
+        }
+        let requested = |name: &str| {
+            self.columns
+                .as_ref()
+                .is_none_or(|columns| columns.iter().any(|c| c == name))
+        };
+        for (name, default) in V1_DEFAULTS {
+            if requested(name) && !self.fields.iter().any(|f| f.name == name) {
+                let series = Series::new(name.into(), vec![default; height]);
+                columns.insert(series.name().clone(), series);
+            }
+        }
+
+        let mut acc = Vec::with_capacity(columns.len());
+        for (name, field) in ReadSchema::new().inner.iter() {
+            match columns.remove(name) {
+                // read_id is converted the same way in every version
+                Some(series) if matches!(field.dtype, pl::ArrowDataType::Extension(_)) => {
+                    acc.push(series)
+                }
+                Some(series) => acc.push(series.cast(&pl::DataType::from_arrow_field(field))?),
+                None => (),
+            }
+        }
+        Ok(DataFrame::from_iter(acc))
+    }
+}
+
+/// Version of the table schemas a Read table with these fields was written
+/// with.
+pub(crate) fn schema_version(fields: &[Field]) -> SchemaVersion {
+    let has = |name: &str| fields.iter().any(|f| f.name == name);
+    if !fields.iter().any(is_struct_dictionary) {
+        SchemaVersion::V3
+    } else if has("num_samples") {
+        SchemaVersion::V2
+    } else if has("num_minknow_events") {
+        SchemaVersion::V1
+    } else {
+        SchemaVersion::V0
+    }
+}
+
+/// Run infos from the `run_info` dictionary of an old Read table record
+/// batch, with one row per dictionary value.
+pub(crate) fn run_info_df(
+    fields: &[Field],
+    batch: RecordBatchT<Box<dyn Array>>,
+) -> Result<DataFrame, Pod5Error> {
+    let Some((field, arr)) = fields
+        .iter()
+        .zip(batch.into_arrays())
+        .find(|(f, _)| f.name == "run_info")
+    else {
+        return Ok(DataFrame::empty());
+    };
+    let values = dictionary_values(field, arr.as_ref())?;
+    let mut acc = Vec::with_capacity(values.fields().len());
+    for (field, arr) in values.fields().iter().zip(values.values()) {
+        acc.push(array_to_series(field, arr.clone())?);
+    }
+    Ok(DataFrame::from_iter(acc))
+}
+
+/// Convert the chunks of a Read table in the current layout into record
+/// batches of the `version` Read table, which must be older than v3.
+///
+/// Arrow IPC files can't replace a dictionary between record batches, so the
+/// dictionaries are built from every chunk and shared by all the batches.
+/// Each read's run info is looked up by acquisition_id in `run_infos`, a
+/// DataFrame in the layout of the current Run Info table.
+pub(crate) fn to_legacy_batches(
+    chunks: &[DataFrame],
+    run_infos: Option<&DataFrame>,
+    version: SchemaVersion,
+) -> Result<Vec<RecordBatchT<Box<dyn Array>>>, WriteError> {
+    let Some((first, rest)) = chunks.split_first() else {
+        return Ok(Vec::new());
+    };
+    let mut table = first.clone();
+    for chunk in rest {
+        table.vstack_mut(chunk)?;
+    }
+    let mut dictionaries = Vec::with_capacity(DICTIONARY_COLUMNS.len());
+    for (name, children) in DICTIONARY_COLUMNS {
+        let dictionary = match name {
+            "run_info" => run_info_dictionary(&table, run_infos)?,
+            _ => struct_dictionary(&table, name, children)?,
+        };
+        dictionaries.push(dictionary);
+    }
+
+    let mut batches = Vec::with_capacity(chunks.len());
+    let mut offset = 0;
+    for chunk in chunks {
+        batches.push(legacy_batch(chunk, offset, &dictionaries, version)?);
+        offset += chunk.height();
+    }
+    Ok(batches)
+}
+
+/// Record batch of the `version` Read table for the rows of `df`, which start
+/// at row `offset` of the table the `dictionaries` were built from.
+fn legacy_batch(
+    df: &DataFrame,
+    offset: usize,
+    dictionaries: &[StructDictionary],
+    version: SchemaVersion,
+) -> Result<RecordBatchT<Box<dyn Array>>, WriteError> {
+    let (schema, arrays) = compat_batch(df)?.into_schema_and_arrays();
+    let mut plain = schema
+        .iter_values()
+        .cloned()
+        .zip(arrays)
+        .map(|(field, arr)| (field.name.clone(), (field, arr)))
+        .collect::<HashMap<_, _>>();
+
+    let mut columns = vec!["read_id", "signal", "pore", "calibration"];
+    columns.extend([
+        "read_number",
+        "start",
+        "median_before",
+        "end_reason",
+        "run_info",
+    ]);
+    if version >= SchemaVersion::V1 {
+        columns.extend(V1_DEFAULTS.map(|(name, _)| name));
+    }
+    if version >= SchemaVersion::V2 {
+        columns.push("num_samples");
+    }
+    let mut fields = ArrowSchema::with_capacity(columns.len());
+    let mut acc = Vec::with_capacity(columns.len());
+    for name in columns {
+        let (field, arr) = match dictionaries.iter().find(|d| d.field.name == name) {
+            Some(dictionary) => dictionary.slice(offset, df.height())?,
+            None => plain.remove(name).expect("checked against ReadSchema"),
+        };
+        fields.insert(field.name.clone(), field);
+        acc.push(arr);
+    }
+    Ok(RecordBatchT::new(df.height(), Arc::new(fields), acc))
+}
+
+/// A single record batch of `df` with its columns converted to POD5 types.
+fn compat_batch(df: &DataFrame) -> Result<RecordBatchT<Box<dyn Array>>, WriteError> {
+    let mut df = df.clone();
+    df.rechunk_mut();
+    let batch = df
+        .iter_chunks(CompatLevel::newest(), false)
+        .next()
+        .expect("rechunked DataFrame has a chunk");
+    Ok(record_batch_to_compat(batch)?)
+}
+
+/// Dictionary of structs from the `children` columns of `df`, with a value
+/// for each distinct combination of them.
+fn struct_dictionary(
+    df: &DataFrame,
+    name: &str,
+    children: &[(&str, &str)],
+) -> Result<StructDictionary, WriteError> {
+    let source = children
+        .iter()
+        .map(|(child, column)| {
+            let column = df.column(column)?;
+            let column = match column.dtype() {
+                pl::DataType::Categorical(..) => column.cast(&pl::DataType::String)?,
+                _ => column.clone(),
+            };
+            Ok(column.with_name((*child).into()))
+        })
+        .collect::<Result<Vec<_>, WriteError>>()?;
+    let source = DataFrame::new(source)?;
+    let (keys, first) = dictionary_keys(&source, name)?;
+    let values = source.take(&first)?;
+    dictionary(name, keys, &values)
+}
+
+/// Dictionary of run info structs for the `run_info` column of `df`.
+fn run_info_dictionary(
+    df: &DataFrame,
+    run_infos: Option<&DataFrame>,
+) -> Result<StructDictionary, WriteError> {
+    let Some(run_infos) = run_infos else {
+        let id = df.column("run_info")?.cast(&pl::DataType::String)?;
+        let id = id.str()?.get(0).unwrap_or_default().to_string();
+        return Err(WriteError::UnknownRunInfo(id));
+    };
+    let source = df
+        .column("run_info")?
+        .cast(&pl::DataType::String)?
+        .into_frame();
+    let (keys, first) = dictionary_keys(&source, "run_info")?;
+    let rows = run_infos
+        .column("acquisition_id")?
+        .str()?
+        .into_iter()
+        .enumerate()
+        .filter_map(|(row, id)| Some((id?, row as IdxSize)))
+        .collect::<HashMap<_, _>>();
+    let acquisition_ids = source.take(&first)?;
+    let idx = acquisition_ids
+        .column("run_info")?
+        .str()?
+        .into_iter()
+        .map(|id| {
+            let id = id.unwrap_or_default();
+            rows.get(id)
+                .copied()
+                .ok_or_else(|| WriteError::UnknownRunInfo(id.to_string()))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let values = run_infos.take(&IdxCa::from_vec("".into(), idx))?;
+    dictionary("run_info", keys, &values)
+}
+
+/// Dictionary key of each row of `source`, numbering distinct rows in the
+/// order they first appear, along with the first row of each.
+fn dictionary_keys(source: &DataFrame, name: &str) -> Result<(Vec<i16>, IdxCa), WriteError> {
+    let groups = source
+        .group_by_stable(source.get_column_names_owned())?
+        .take_groups();
+    if groups.len() > i16::MAX as usize + 1 {
+        return Err(WriteError::TooManyDictionaryValues(name.to_string()));
+    }
+    let mut keys = vec![0i16; source.height()];
+    let mut first = Vec::with_capacity(groups.len());
+    for (key, group) in groups.iter().enumerate() {
+        first.push(group.first());
+        match group {
+            GroupsIndicator::Idx((_, rows)) => {
+                rows.iter().for_each(|&row| keys[row as usize] = key as i16)
+            }
+            GroupsIndicator::Slice([start, len]) => {
+                keys[start as usize..(start + len) as usize].fill(key as i16)
+            }
+        }
+    }
+    Ok((keys, IdxCa::from_vec("".into(), first)))
+}
+
+fn dictionary(
+    name: &str,
+    keys: Vec<i16>,
+    values: &DataFrame,
+) -> Result<StructDictionary, WriteError> {
+    let (schema, arrays) = compat_batch(values)?.into_schema_and_arrays();
+    let struct_dtype = pl::ArrowDataType::Struct(schema.iter_values().cloned().collect());
+    let values = StructArray::new(struct_dtype.clone(), values.height(), arrays, None);
+    let dtype = pl::ArrowDataType::Dictionary(IntegerType::Int16, Box::new(struct_dtype), false);
+    Ok(StructDictionary {
+        field: Field::new(name.into(), dtype, true),
+        keys,
+        values: values.boxed(),
+    })
+}
+
+/// A dictionary column of a whole Read table.
+struct StructDictionary {
+    field: Field,
+    /// Key of every row of the table
+    keys: Vec<i16>,
+    values: Box<dyn Array>,
+}
+
+impl StructDictionary {
+    /// The column for rows `offset..offset + len`, with every value of the
+    /// dictionary so that each batch has the same one.
+    fn slice(&self, offset: usize, len: usize) -> Result<(Field, Box<dyn Array>), WriteError> {
+        let keys = PrimitiveArray::from_slice(&self.keys[offset..offset + len]);
+        let dictionary =
+            DictionaryArray::try_new(self.field.dtype.clone(), keys, self.values.clone())?;
+        Ok((self.field.clone(), dictionary.boxed()))
+    }
+}
+
+/// IPC fields for writing a Read table older than v3.
+///
+/// polars-arrow's default fields give a dictionary its value type as a
+/// child, but it encodes the values of a dictionary as if they were the
+/// dictionary's own children, so dictionaries of structs fail to write
+/// unless the struct's fields are put directly on the dictionary.
+pub(crate) fn ipc_fields(schema: &ArrowSchema) -> Vec<IpcField> {
+    fn ipc_field(dtype: &pl::ArrowDataType, id: &mut i64) -> IpcField {
+        use pl::ArrowDataType::*;
+        match dtype.to_logical_type() {
+            Dictionary(_, values, _) => {
+                let dictionary_id = Some(*id);
+                *id += 1;
+                IpcField {
+                    fields: ipc_field(values, id).fields,
+                    dictionary_id,
+                }
+            }
+            Struct(fields) => IpcField {
+                fields: fields.iter().map(|f| ipc_field(f.dtype(), id)).collect(),
+                dictionary_id: None,
+            },
+            Map(inner, _) | List(inner) | LargeList(inner) | FixedSizeList(inner, _) => IpcField {
+                fields: vec![ipc_field(inner.dtype(), id)],
+                dictionary_id: None,
+            },
+            _ => IpcField {
+                fields: Vec::new(),
+                dictionary_id: None,
+            },
+        }
+    }
+    let mut id = 0;
+    schema
+        .iter_values()
+        .map(|field| ipc_field(field.dtype(), &mut id))
+        .collect()
+}
+
+fn is_struct_dictionary(field: &Field) -> bool {
+    matches!(
+        field.dtype.to_logical_type(),
+        pl::ArrowDataType::Dictionary(_, values, _)
+            if matches!(values.as_ref(), pl::ArrowDataType::Struct(_))
+    )
+}
+
+fn dictionary_values<'a>(field: &Field, arr: &'a dyn Array) -> Result<&'a StructArray, Pod5Error> {
+    arr.as_any()
+        .downcast_ref::<DictionaryArray<i16>>()
+        .and_then(|dict| dict.values().as_any().downcast_ref::<StructArray>())
+        .ok_or_else(|| Pod5Error::UnsupportedDataType {
+            column: field.name.to_string(),
+            dtype: format!("{:?}", field.dtype),
+        })
+}
+
+/// Look up each row of a dictionary of structs, returning a column for each
+/// of the wanted struct fields, renamed to its current column name.
+fn expand_dictionary(
+    field: &Field,
+    arr: Box<dyn Array>,
+    children: &[(&str, &str)],
+) -> Result<Vec<Series>, Pod5Error> {
+    let values = dictionary_values(field, arr.as_ref())?;
+    let keys = arr
+        .as_any()
+        .downcast_ref::<DictionaryArray<i16>>()
+        .expect("checked by dictionary_values")
+        .keys();
+    let idx = keys
+        .iter()
+        .map(|key| key.map(|&key| key as IdxSize))
+        .collect::<IdxCa>();
+    let mut acc = Vec::with_capacity(children.len());
+    for (child, arr) in values.fields().iter().zip(values.values()) {
+        if let Some((_, name)) = children.iter().find(|(name, _)| *name == child.name) {
+            let series = array_to_series(child, arr.clone())?.take(&idx)?;
+            acc.push(series.with_name((*name).into()));
+        }
+    }
+    Ok(acc)
+}
+
+/// Total samples of each read, from the Signal table rows in the `signal`
+/// column.
+fn num_samples(signal: &Series, samples: &[u32]) -> Result<Series, Pod5Error> {
+    let mut acc = Vec::with_capacity(signal.len());
+    for rows in signal.list()?.into_iter() {
+        let mut total = 0u64;
+        if let Some(rows) = rows {
+            for row in rows.u64()?.into_no_null_iter() {
+                let row_samples = samples
+                    .get(row as usize)
+                    .ok_or(Pod5Error::SignalRowOutOfBounds(row, samples.len() as u64))?;
+                total += *row_samples as u64;
+            }
+        }
+        acc.push(total);
+    }
+    Ok(Series::new("num_samples".into(), acc))
+}
+
+#[cfg(test)]
+mod test {
+    use std::{fs::File, io::Cursor};
+
+    use polars_arrow::datatypes::{ArrowDataType, ExtensionType};
+
+    use super::*;
+    use crate::{
+        dataframe::ReadDataFrame,
+        reader::Reader,
+        writer::{Writer, WriterOptions},
+    };
+
+    const PATHS: [&str; 4] = [
+        "../extra/multi_fast5_zip_v0.pod5",
+        "../extra/multi_fast5_zip_v1.pod5",
+        "../extra/multi_fast5_zip_v2.pod5",
+        "../extra/multi_fast5_zip_v3.pod5",
+    ];
+
+    /// Every table of the file, with categorical columns cast to strings since
+    /// their dictionaries can differ between files.
+    fn tables<R: std::io::Read + std::io::Seek>(
+        reader: &mut Reader<R>,
+    ) -> eyre::Result<[DataFrame; 3]> {
+        fn concat<I: Iterator<Item = Result<DataFrame, Pod5Error>>>(
+            dfs: I,
+        ) -> eyre::Result<DataFrame> {
+            let mut acc = DataFrame::empty();
+            for df in dfs {
+                acc.vstack_mut(&df?)?;
+            }
+            let columns = acc
+                .get_columns()
+                .iter()
+                .map(|c| match c.dtype() {
+                    pl::DataType::Categorical(..) => c.cast(&pl::DataType::String),
+                    _ => Ok(c.clone()),
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(DataFrame::new(columns)?)
+        }
+        Ok([
+            concat(reader.read_dfs()?.map(|df| df.map(|df| df.0)))?,
+            concat(reader.run_info_dfs()?.map(|df| df.map(|df| df.0)))?,
+            concat(reader.signal_dfs()?.map(|df| df.map(|df| df.0)))?,
+        ])
+    }
+
+    #[test]
+    fn test_legacy_matches_v3() -> eyre::Result<()> {
+        let expected = tables(&mut Reader::from_reader(File::open(PATHS[3])?)?)?;
+        for path in &PATHS[..3] {
+            let mut reader = Reader::from_reader(File::open(path)?)?;
+            let [reads, run_info, signal] = tables(&mut reader)?;
+            assert_eq!(reads, expected[0], "{path}");
+            assert_eq!(run_info, expected[1], "{path}");
+            assert_eq!(signal, expected[2], "{path}");
+            assert_eq!(reader.reads()?.count(), reads.height(), "{path}");
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_legacy_projection() -> eyre::Result<()> {
+        let columns = ["read_id".into(), "channel".into(), "num_samples".into()];
+        for path in PATHS {
+            let mut reader = Reader::from_reader(File::open(path)?)?;
+            let df = reader.read_dfs_with_columns(&columns)?.next().unwrap()?.0;
+            assert_eq!(df.get_column_names(), ["read_id", "num_samples", "channel"]);
+            let full = reader.read_dfs()?.next().unwrap()?.0;
+            assert_eq!(df, full.select(df.get_column_names_owned())?, "{path}");
+
+            // Columns added after the file was written get their defaults
+            let columns = ["read_id".into(), "num_minknow_events".into()];
+            let df = reader.read_dfs_with_columns(&columns)?.next().unwrap()?.0;
+            assert_eq!(df.get_column_names(), ["read_id", "num_minknow_events"]);
+            assert_eq!(df, full.select(columns)?, "{path}");
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_roundtrip() -> eyre::Result<()> {
+        for path in PATHS {
+            let mut reader = Reader::from_reader(File::open(path)?)?;
+            let run_info = reader.run_info_dfs()?.collect::<Result<Vec<_>, _>>()?;
+            let reads = reader.read_dfs()?.collect::<Result<Vec<_>, _>>()?;
+            let signal = reader.signal_dfs()?.collect::<Result<Vec<_>, _>>()?;
+
+            let mut output = Cursor::new(Vec::new());
+            let mut writer = Writer::from_writer(&mut output)?;
+            writer.with_guard(|g| run_info.iter().try_for_each(|df| g.write_batch(df)))?;
+            writer.with_guard(|g| reads.iter().try_for_each(|df| g.write_batch(df)))?;
+            writer.with_guard(|g| signal.iter().try_for_each(|df| g.write_batch(df)))?;
+            writer.finish()?;
+
+            let mut written = Reader::from_reader(Cursor::new(output.into_inner()))?;
+            assert_eq!(tables(&mut written)?, tables(&mut reader)?, "{path}");
+        }
+        Ok(())
+    }
+
+    /// Column names and types of the Read table, ignoring extension type
+    /// metadata.
+    fn read_columns<R: std::io::Read + std::io::Seek>(
+        reader: &mut Reader<R>,
+    ) -> eyre::Result<Vec<(String, ArrowDataType)>> {
+        Ok(reader
+            .read_dfs()?
+            .table_reader
+            .schema()
+            .iter_values()
+            .map(|field| {
+                let dtype = match &field.dtype {
+                    ArrowDataType::Extension(ext) => {
+                        ArrowDataType::Extension(Box::new(ExtensionType {
+                            metadata: None,
+                            ..(**ext).clone()
+                        }))
+                    }
+                    dtype => dtype.clone(),
+                };
+                (field.name.to_string(), dtype)
+            })
+            .collect())
+    }
+
+    #[test]
+    fn test_write_legacy() -> eyre::Result<()> {
+        let mut reader = Reader::from_reader(File::open(PATHS[3])?)?;
+        let run_info = reader.run_info_dfs()?.collect::<Result<Vec<_>, _>>()?;
+        let reads = reader.read_dfs()?.collect::<Result<Vec<_>, _>>()?;
+        let signal = reader.signal_dfs()?.collect::<Result<Vec<_>, _>>()?;
+        let expected = tables(&mut reader)?;
+
+        let versions = [SchemaVersion::V0, SchemaVersion::V1, SchemaVersion::V2];
+        for (version, path) in versions.into_iter().zip(PATHS) {
+            let mut output = Cursor::new(Vec::new());
+            let options = WriterOptions::new().with_schema_version(version);
+            let mut writer = Writer::from_writer_with_options(&mut output, options)?;
+            writer.with_guard(|g| run_info.iter().try_for_each(|df| g.write_batch(df)))?;
+            writer.with_guard(|g| reads.iter().try_for_each(|df| g.write_batch(df)))?;
+            writer.with_guard(|g| signal.iter().try_for_each(|df| g.write_batch(df)))?;
+            writer.finish()?;
+
+            let mut written = Reader::from_reader(Cursor::new(output.into_inner()))?;
+            let mut original = Reader::from_reader(File::open(path)?)?;
+            assert_eq!(
+                read_columns(&mut written)?,
+                read_columns(&mut original)?,
+                "{path}"
+            );
+            assert_eq!(tables(&mut written)?, expected, "{path}");
+            assert_eq!(
+                written.footer_info()?.pod5_version(),
+                Some(version.pod5_version())
+            );
+            assert_eq!(written.tables()?.len(), 2);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_legacy_batches() -> eyre::Result<()> {
+        let mut reader = Reader::from_reader(File::open(PATHS[3])?)?;
+        let run_info = reader.run_info_dfs()?.collect::<Result<Vec<_>, _>>()?;
+        let reads = reader.read_dfs()?.collect::<Result<Vec<_>, _>>()?;
+        let signal = reader.signal_dfs()?.collect::<Result<Vec<_>, _>>()?;
+        let expected = tables(&mut reader)?;
+
+        // One read per write, rechunked into batches of three
+        let options = WriterOptions::new().with_schema_version(SchemaVersion::V1);
+        let mut output = Cursor::new(Vec::new());
+        let mut writer = Writer::from_writer_with_options(&mut output, options)?.with_batch_size(3);
+        writer.with_guard(|g| run_info.iter().try_for_each(|df| g.write_batch(df)))?;
+        writer.with_guard::<ReadDataFrame, _>(|g| {
+            for df in &reads {
+                for row in 0..df.0.height() {
+                    g.write_batch(&ReadDataFrame(df.0.slice(row as i64, 1)))?;
+                }
+            }
+            Ok(())
+        })?;
+        writer.with_guard(|g| signal.iter().try_for_each(|df| g.write_batch(df)))?;
+        writer.finish()?;
+
+        let mut written = Reader::from_reader(Cursor::new(output.into_inner()))?;
+        let heights = written
+            .read_dfs()?
+            .map(|df| df.map(|df| df.0.height()))
+            .collect::<Result<Vec<_>, _>>()?;
+        assert!(heights.len() > 1);
+        assert!(heights.iter().all(|&height| height <= 3));
+        assert_eq!(tables(&mut written)?, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_legacy_unknown_run_info() -> eyre::Result<()> {
+        let mut reader = Reader::from_reader(File::open(PATHS[3])?)?;
+        let reads = reader.read_dfs()?.next().unwrap()?;
+
+        let options = WriterOptions::new().with_schema_version(SchemaVersion::V2);
+        let mut writer = Writer::from_writer_with_options(Cursor::new(Vec::new()), options)?;
+        let res = writer.with_guard(|g| g.write_batch(&reads));
+        assert!(matches!(res, Err(WriteError::UnknownRunInfo(_))));
+        Ok(())
+    }
+}
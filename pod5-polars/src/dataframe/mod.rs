@@ -9,27 +9,36 @@
 // 2. Smaller types, aka not Large*
 // 3. Support Extension data types, at least casting down to regular type
 use std::{
-    collections::HashMap,
-    io::{Cursor, Read, Seek, SeekFrom},
+    collections::{BTreeMap, HashMap, HashSet},
+    io::{Cursor, Read, Seek},
+    num::NonZeroUsize,
+    sync::{Arc, Mutex},
 };
 
+use lru::LruCache;
+
 use polars::{
     error::PolarsError,
     frame::DataFrame,
-    lazy::{dsl::GetOutput, frame::IntoLazy},
-    prelude::{self as pl, Column, NamedFrom},
+    prelude::{self as pl, BooleanChunked, Column, NamedFrom, PlSmallStr},
     series::Series,
 };
 use polars_arrow::{
-    datatypes::Field,
+    array::Array,
+    datatypes::{ArrowSchema, Field, Metadata},
     io::ipc::read::{FileReader, read_file_metadata},
+    record_batch::RecordBatchT,
 };
 
 pub(crate) mod compatibility;
+pub(crate) mod legacy;
 pub(crate) mod schema;
+pub mod window;
 
+use pod5_format::footer_generated::minknow::reads_format::ContentType;
 use svb16::decode;
 
+use self::{legacy::LegacyReads, window::TableWindow};
 use crate::error::Pod5Error;
 
 /// DataFrame wrapper for the POD5 Signal table.
@@ -42,95 +51,307 @@ impl SignalDataFrame {
     /// Assumes that there are two columns, samples (u32) representing the
     /// number of signal measurements, and signal, representing the
     /// compressed signal data (binary)
+    ///
+    /// Rows that fail to decode are reported by their row in this DataFrame.
     pub fn decompress_signal(self) -> Result<Self, Pod5Error> {
-        let res = self
-            .0
-            .lazy()
-            .with_column(
-                pl::as_struct(vec![pl::col("samples"), pl::col("signal")])
-                    .map(decompress_signal_series, GetOutput::default())
-                    .alias("signal"),
-            )
-            .collect()
-            .map(Self)?;
-        Ok(res)
+        self.decompress_signal_rows(|row| row as u64, None)
+    }
+
+    /// Same as [`SignalDataFrame::decompress_signal`], with `signal_row`
+    /// mapping a row of this DataFrame to its Signal table row for errors.
+    /// With a `corrupt` log, rows that fail to decode are recorded there and
+    /// given null signal instead.
+    pub(crate) fn decompress_signal_rows<F>(
+        mut self,
+        signal_row: F,
+        corrupt: Option<&CorruptSignalLog>,
+    ) -> Result<Self, Pod5Error>
+    where
+        F: Fn(usize) -> u64,
+    {
+        let signal = decompress_signal_column(&self.0, signal_row, corrupt)?;
+        self.0.with_column(signal)?;
+        Ok(self)
     }
 
     /// Convert i16 ADC signal data into f32 picoamps
-    pub fn to_picoamps(mut self, calibration: &Calibration) -> Self {
-        let adcs = self.0["read_id"]
-            .str()
-            .unwrap()
-            .into_iter()
-            .flatten()
-            .map(|rid| calibration.0.get(rid).unwrap())
-            .collect::<Vec<_>>();
+    ///
+    /// Returns [`Pod5Error::CompressedSignal`] for signal read in raw mode,
+    /// which has to go through [`SignalDataFrame::decompress_signal`] first.
+    pub fn to_picoamps(mut self, calibration: &Calibration) -> Result<Self, Pod5Error> {
+        let signal = self.samples()?;
+        let adcs = calibration.for_reads(&self.0)?;
         let offsets = Column::from(Series::from_iter(adcs.iter().map(|adc| adc.offset)));
         let scale = Column::from(Series::from_iter(adcs.iter().map(|adc| adc.scale)));
-        let res = (&self.0["signal"] + &offsets).unwrap();
-        let res = (res * scale).unwrap();
-        self.0.with_column(res).unwrap();
-        self
+        let res = (signal + &offsets)?;
+        let res = (res * scale)?;
+        self.0.with_column(res)?;
+        Ok(self)
     }
 
     /// Convert f32 picoamps signal data into i16 ADC
     // TOOD Make sure to be able to call and still do the conversion for the writer
     #[allow(dead_code)]
-    pub(crate) fn with_adc(mut self, calibration: &Calibration) -> Self {
-        let adcs = self.0["read_id"]
-            .str()
-            .unwrap()
-            .into_iter()
-            .flatten()
-            .map(|rid| calibration.0.get(rid).unwrap())
-            .collect::<Vec<_>>();
+    pub(crate) fn with_adc(mut self, calibration: &Calibration) -> Result<Self, Pod5Error> {
+        let signal = self.samples()?;
+        let adcs = calibration.for_reads(&self.0)?;
         let offsets = Column::from(Series::from_iter(adcs.iter().map(|adc| adc.offset)));
         let scale = Column::from(Series::from_iter(adcs.iter().map(|adc| adc.scale)));
-        let res = (&self.0["signal"] / &scale).unwrap();
-        let res = (res - offsets)
-            .unwrap()
-            .cast(&pl::DataType::List(Box::new(pl::DataType::Int16)))
-            .unwrap();
-        self.0.with_column(res).unwrap();
-        self
+        let res = (signal / &scale)?;
+        let res = (res - offsets)?.cast(&pl::DataType::List(Box::new(pl::DataType::Int16)))?;
+        self.0.with_column(res)?;
+        Ok(self)
     }
 
     /// Get the inner `polars` DataFrame.
     pub fn into_inner(self) -> DataFrame {
         self.0
     }
+
+    /// The decompressed `signal` column, rejecting signal that is still VBZ
+    /// compressed.
+    fn samples(&self) -> Result<&Column, Pod5Error> {
+        let signal = self.0.column("signal")?;
+        match signal.dtype() {
+            pl::DataType::Binary => Err(Pod5Error::CompressedSignal),
+            _ => Ok(signal),
+        }
+    }
 }
 
-pub struct SignalDataFrameIter {
+pub struct SignalDataFrameIter<R: Read + Seek> {
     pub(crate) fields: Vec<Field>,
-    pub(crate) table_reader: FileReader<Cursor<Vec<u8>>>,
+    pub(crate) table_reader: FileReader<TableWindow<R>>,
+    /// Signal table row of the start of the next batch
+    next_row: u64,
+    corrupt: Option<CorruptSignalLog>,
+    /// Leave the signal VBZ compressed
+    raw: bool,
 }
 
-impl SignalDataFrameIter {
-    pub(crate) fn new<R: Read + Seek>(
+impl<R: Read + Seek> SignalDataFrameIter<R> {
+    pub(crate) fn new(
         offset: u64,
         length: u64,
-        file: &mut R,
+        file: Arc<Mutex<R>>,
+        corrupt: Option<CorruptSignalLog>,
     ) -> Result<Self, Pod5Error> {
         let (fields, table_reader) =
             read_to_dataframe(offset, length, Pod5Error::SignalTableMissing, file)?;
         Ok(Self {
             fields,
             table_reader,
+            next_row: 0,
+            corrupt,
+            raw: false,
+        })
+    }
+
+    /// Yield the signal column as it is stored, VBZ compressed binary,
+    /// instead of decompressing it.
+    ///
+    /// Writing these DataFrames copies the compressed signal to the new file
+    /// byte for byte, which is much faster than decompressing and encoding
+    /// it again when copying, subsetting or merging files. Since nothing is
+    /// decoded, corrupt signal isn't detected. The `signal` column is Binary
+    /// rather than a list of samples, so [`SignalDataFrame::to_picoamps`]
+    /// returns [`Pod5Error::CompressedSignal`] until
+    /// [`SignalDataFrame::decompress_signal`] is called.
+    pub fn raw(mut self) -> Self {
+        self.raw = true;
+        self
+    }
+}
+
+/// Random access to rows of an embedded table.
+///
+/// Keeps track of how many rows are in each record batch so that individual
+/// rows can be fetched without iterating over the whole table. Only record
+/// batches containing a requested row are read, and recently read batches are
+/// cached.
+struct BatchedTable<R: Read + Seek> {
+    fields: Vec<Field>,
+    table_reader: FileReader<TableWindow<R>>,
+    /// Set for the Read table of files older than v3
+    legacy: Option<LegacyReads>,
+    /// Index of the first row of each record batch, with the total number of
+    /// rows as the last element.
+    batch_offsets: Vec<u64>,
+    cache: LruCache<usize, DataFrame>,
+}
+
+impl<R: Read + Seek> BatchedTable<R> {
+    const CACHED_BATCHES: NonZeroUsize = NonZeroUsize::new(8).unwrap();
+
+    fn new(
+        (fields, mut table_reader): TableReader<R>,
+        legacy: Option<LegacyReads>,
+    ) -> Result<Self, Pod5Error> {
+        let mut batch_offsets = vec![0];
+        let mut total = 0;
+        while let Some(batch) = table_reader.next_record_batch() {
+            let rows = batch
+                .and_then(|b| Ok(b.length()?))
+                .map_err(Pod5Error::PolarsError)?;
+            total += rows as u64;
+            batch_offsets.push(total);
+        }
+        Ok(Self {
+            fields,
+            table_reader,
+            legacy,
+            batch_offsets,
+            cache: LruCache::new(Self::CACHED_BATCHES),
         })
     }
+
+    fn num_rows(&self) -> u64 {
+        self.batch_offsets.last().copied().unwrap_or_default()
+    }
+
+    /// Find the record batch containing `row` and the row's position within
+    /// that batch, or `None` if the row is out of bounds.
+    fn locate(&self, row: u64) -> Option<(usize, i64)> {
+        if row >= self.num_rows() {
+            return None;
+        }
+        let batch = self.batch_offsets.partition_point(|&start| start <= row) - 1;
+        Some((batch, (row - self.batch_offsets[batch]) as i64))
+    }
+
+    fn batch(&mut self, idx: usize) -> Result<&DataFrame, Pod5Error> {
+        if !self.cache.contains(&idx) {
+            self.table_reader.set_current_block(idx);
+            let df = get_next_read_df(&self.fields, &mut self.table_reader, self.legacy.as_ref())
+                .ok_or_else(|| {
+                    PolarsError::NoData(format!("record batch {idx} missing from table").into())
+                })??;
+            self.cache.put(idx, df);
+        }
+        Ok(self.cache.get(&idx).expect("batch was just inserted"))
+    }
+
+    /// Fetch the given rows in the order given. `out_of_bounds` builds the
+    /// error for a row past the end of the table from the row and the number
+    /// of rows.
+    fn take_rows(
+        &mut self,
+        rows: &[u64],
+        out_of_bounds: fn(u64, u64) -> Pod5Error,
+    ) -> Result<Option<DataFrame>, Pod5Error> {
+        let mut acc: Option<DataFrame> = None;
+        for &row in rows {
+            let (batch, local) = self
+                .locate(row)
+                .ok_or_else(|| out_of_bounds(row, self.num_rows()))?;
+            let df = self.batch(batch)?.slice(local, 1);
+            match acc.as_mut() {
+                Some(acc) => {
+                    acc.vstack_mut(&df)?;
+                }
+                None => acc = Some(df),
+            }
+        }
+        Ok(acc)
+    }
+}
+
+/// Random access to rows of the POD5 Signal table.
+///
+/// Only record batches containing a requested row are read, and recently
+/// read batches are cached.
+pub struct SignalTableReader<R: Read + Seek> {
+    table: BatchedTable<R>,
+    corrupt: Option<CorruptSignalLog>,
+    /// Leave the signal VBZ compressed
+    raw: bool,
+}
+
+impl<R: Read + Seek> SignalTableReader<R> {
+    pub(crate) fn new(
+        offset: u64,
+        length: u64,
+        file: Arc<Mutex<R>>,
+        corrupt: Option<CorruptSignalLog>,
+    ) -> Result<Self, Pod5Error> {
+        let table = read_to_dataframe(offset, length, Pod5Error::SignalTableMissing, file)?;
+        Ok(Self {
+            table: BatchedTable::new(table, None)?,
+            corrupt,
+            raw: false,
+        })
+    }
+
+    /// Fetch rows with the signal VBZ compressed, as it is stored. See
+    /// [`SignalDataFrameIter::raw`].
+    pub fn raw(mut self) -> Self {
+        self.raw = true;
+        self
+    }
+
+    /// Total number of rows in the Signal table.
+    pub fn num_rows(&self) -> u64 {
+        self.table.num_rows()
+    }
+
+    /// Fetch the given rows of the Signal table, in the order given, with the
+    /// signal decompressed unless reading [`SignalTableReader::raw`].
+    pub fn take_rows(&mut self, rows: &[u64]) -> Result<SignalDataFrame, Pod5Error> {
+        match self
+            .table
+            .take_rows(rows, Pod5Error::SignalRowOutOfBounds)?
+        {
+            Some(df) if self.raw => Ok(SignalDataFrame(df)),
+            Some(df) => {
+                SignalDataFrame(df).decompress_signal_rows(|row| rows[row], self.corrupt.as_ref())
+            }
+            None => Ok(SignalDataFrame::default()),
+        }
+    }
 }
 
-impl Iterator for SignalDataFrameIter {
+/// Random access to rows of the POD5 Read table, used together with a
+/// [`ReadIdIndex`](crate::index::ReadIdIndex) to look up reads without
+/// scanning the table.
+pub struct ReadTableReader<R: Read + Seek>(BatchedTable<R>);
+
+impl<R: Read + Seek> ReadTableReader<R> {
+    pub(crate) fn new(
+        offset: u64,
+        length: u64,
+        file: Arc<Mutex<R>>,
+        legacy: Option<LegacyReads>,
+    ) -> Result<Self, Pod5Error> {
+        let table = read_to_dataframe(offset, length, Pod5Error::ReadTableMissing, file)?;
+        Ok(Self(BatchedTable::new(table, legacy)?))
+    }
+
+    /// Total number of rows in the Read table.
+    pub fn num_rows(&self) -> u64 {
+        self.0.num_rows()
+    }
+
+    /// Fetch the given rows of the Read table, in the order given.
+    pub fn take_rows(&mut self, rows: &[u64]) -> Result<ReadDataFrame, Pod5Error> {
+        let df = self.0.take_rows(rows, Pod5Error::ReadRowOutOfBounds)?;
+        Ok(ReadDataFrame(df.unwrap_or_default()))
+    }
+}
+
+impl<R: Read + Seek> Iterator for SignalDataFrameIter<R> {
     type Item = Result<SignalDataFrame, Pod5Error>;
 
-    /// TODO: Check when Result happens
     fn next(&mut self) -> Option<Self::Item> {
-        let df = get_next_df(&self.fields, &mut self.table_reader);
-        df.map(|res| {
-            res.map(SignalDataFrame)
-                .and_then(|sdf| sdf.decompress_signal())
-        })
+        let df = get_next_df(&self.fields, &mut self.table_reader)?;
+        Some(df.and_then(|df| {
+            let first_row = self.next_row;
+            self.next_row += df.height() as u64;
+            if self.raw {
+                return Ok(SignalDataFrame(df));
+            }
+            SignalDataFrame(df)
+                .decompress_signal_rows(|row| first_row + row as u64, self.corrupt.as_ref())
+        }))
     }
 }
 
@@ -148,82 +369,173 @@ impl ReadDataFrame {
     /// this method if you want to read the view the UUID in ASCII. The
     /// `col_name` can be any existing column or a new column that will be
     /// added to the end of the DataFrame.
-    pub fn parse_read_ids(self, col_name: &str) -> Result<Self, Pod5Error> {
-        let res = self
-            .0
-            .lazy()
-            .with_column(
-                pl::col("read_id")
-                    .map(parse_uuid_from_read_id, GetOutput::default())
-                    .alias(col_name),
-            )
-            .collect()?;
-        Ok(Self(res))
+    pub fn parse_read_ids(mut self, col_name: &str) -> Result<Self, Pod5Error> {
+        let read_ids = parse_uuid_from_read_id(self.0.column("read_id")?)?;
+        self.0.with_column(read_ids.with_name(col_name.into()))?;
+        Ok(self)
     }
 }
 
-pub struct ReadDataFrameIter {
+pub struct ReadDataFrameIter<R: Read + Seek> {
     pub(crate) fields: Vec<Field>,
-    pub(crate) table_reader: FileReader<Cursor<Vec<u8>>>,
+    pub(crate) table_reader: FileReader<TableWindow<R>>,
+    /// Set for files older than v3
+    legacy: Option<LegacyReads>,
+    /// Columns to keep after converting the record batches of an older file
+    columns: Option<Vec<PlSmallStr>>,
 }
 
-impl ReadDataFrameIter {
+impl<R: Read + Seek> ReadDataFrameIter<R> {
     pub fn fields(&self) -> &[Field] {
         self.fields.as_ref()
     }
 
-    pub(crate) fn new<R: Read + Seek>(
+    /// Whether the file is older than v3, in which case [`fields`] are the
+    /// fields stored in the file rather than the columns of the returned
+    /// DataFrames, which use the current [`ReadSchema`] layout.
+    ///
+    /// [`fields`]: ReadDataFrameIter::fields
+    /// [`ReadSchema`]: schema::reads_schema::ReadSchema
+    pub(crate) fn is_legacy(&self) -> bool {
+        self.legacy.is_some()
+    }
+
+    pub(crate) fn new(
         offset: u64,
         length: u64,
-        file: &mut R,
+        file: Arc<Mutex<R>>,
+        legacy: Option<LegacyReads>,
     ) -> Result<Self, Pod5Error> {
         let (fields, table_reader) =
             read_to_dataframe(offset, length, Pod5Error::ReadTableMissing, file)?;
         Ok(Self {
             fields,
             table_reader,
+            legacy,
+            columns: None,
+        })
+    }
+
+    pub(crate) fn with_columns(
+        offset: u64,
+        length: u64,
+        file: Arc<Mutex<R>>,
+        columns: &[PlSmallStr],
+        legacy: Option<LegacyReads>,
+    ) -> Result<Self, Pod5Error> {
+        let legacy = legacy.map(|legacy| legacy.with_columns(columns));
+        let sources = legacy.as_ref().map(|legacy| legacy.source_columns(columns));
+        let (fields, table_reader) = read_to_dataframe_projected(
+            offset,
+            length,
+            Pod5Error::ReadTableMissing,
+            file,
+            Some(sources.as_deref().unwrap_or(columns)),
+        )?;
+        Ok(Self {
+            fields,
+            table_reader,
+            columns: legacy.as_ref().map(|_| columns.to_vec()),
+            legacy,
         })
     }
 
-    pub fn into_calibration(self) -> Calibration {
+    pub fn into_calibration(self) -> Result<Calibration, Pod5Error> {
         Calibration::from_read_dfs(self)
     }
 }
 
-impl Iterator for ReadDataFrameIter {
+impl<R: Read + Seek> Iterator for ReadDataFrameIter<R> {
     type Item = Result<ReadDataFrame, Pod5Error>;
     /// TODO: Check when Result happens
     fn next(&mut self) -> Option<Self::Item> {
-        let df = get_next_df(&self.fields, &mut self.table_reader);
-        df.map(|res| res.map(ReadDataFrame))
+        let df = get_next_read_df(&self.fields, &mut self.table_reader, self.legacy.as_ref());
+        df.map(|res| {
+            let df = res?;
+            let df = match &self.columns {
+                Some(columns) => {
+                    let names = df
+                        .get_column_names_owned()
+                        .into_iter()
+                        .filter(|name| columns.contains(name));
+                    df.select(names)?
+                }
+                None => df,
+            };
+            Ok(ReadDataFrame(df))
+        })
     }
 }
 
-pub struct RunInfoDataFrameIter {
+pub struct RunInfoDataFrameIter<R: Read + Seek> {
     pub(crate) fields: Vec<Field>,
-    pub(crate) table_reader: FileReader<Cursor<Vec<u8>>>,
+    pub(crate) table_reader: FileReader<TableWindow<R>>,
+    /// For files older than v3 the run infos are read from the `run_info`
+    /// dictionary of the Read table instead, which repeats in every record
+    /// batch. Holds the acquisition_ids already returned.
+    legacy: Option<HashSet<String>>,
 }
 
-impl RunInfoDataFrameIter {
-    pub(crate) fn new<R: Read + Seek>(
-        offset: u64,
-        length: u64,
-        file: &mut R,
-    ) -> Result<Self, Pod5Error> {
+impl<R: Read + Seek> RunInfoDataFrameIter<R> {
+    pub(crate) fn new(offset: u64, length: u64, file: Arc<Mutex<R>>) -> Result<Self, Pod5Error> {
         let (fields, table_reader) =
             read_to_dataframe(offset, length, Pod5Error::RunInfoTableMissing, file)?;
         Ok(Self {
             fields,
             table_reader,
+            legacy: None,
         })
     }
+
+    /// Read the run infos from the Read table at `offset..offset + length`
+    /// of a file older than v3.
+    pub(crate) fn legacy(offset: u64, length: u64, file: Arc<Mutex<R>>) -> Result<Self, Pod5Error> {
+        let (fields, table_reader) = read_to_dataframe_projected(
+            offset,
+            length,
+            Pod5Error::ReadTableMissing,
+            file,
+            Some(&["run_info".into()]),
+        )?;
+        Ok(Self {
+            fields,
+            table_reader,
+            legacy: Some(HashSet::new()),
+        })
+    }
+
+    fn next_legacy(&mut self) -> Option<Result<DataFrame, Pod5Error>> {
+        let seen = self.legacy.as_mut()?;
+        loop {
+            let batch = match self.table_reader.next()? {
+                Ok(batch) => batch,
+                Err(e) => return Some(Err(e.into())),
+            };
+            let df = legacy::run_info_df(&self.fields, batch).and_then(|df| {
+                let new = df
+                    .column("acquisition_id")?
+                    .str()?
+                    .into_iter()
+                    .map(|id| seen.insert(id.unwrap_or_default().to_string()))
+                    .collect::<BooleanChunked>();
+                Ok(df.filter(&new)?)
+            });
+            match df {
+                Ok(df) if df.is_empty() => continue,
+                df => return Some(df),
+            }
+        }
+    }
 }
 
-impl Iterator for RunInfoDataFrameIter {
+impl<R: Read + Seek> Iterator for RunInfoDataFrameIter<R> {
     type Item = Result<RunInfoDataFrame, Pod5Error>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        let df = get_next_df(&self.fields, &mut self.table_reader);
+        let df = match self.legacy {
+            Some(_) => self.next_legacy(),
+            None => get_next_df(&self.fields, &mut self.table_reader),
+        };
         df.map(|res| res.map(RunInfoDataFrame))
     }
 }
@@ -237,78 +549,390 @@ impl RunInfoDataFrame {
     }
 }
 
-pub(crate) fn get_next_df(
+/// DataFrame wrapper for a user table embedded as `OtherIndex`, such as
+/// basecall summaries or per-read labels.
+///
+/// The name is stored in the table's Arrow schema metadata so the table can
+/// be found again with
+/// [`Reader::other_dfs`](crate::reader::Reader::other_dfs).
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct OtherDataFrame {
+    pub(crate) name: String,
+    pub(crate) df: DataFrame,
+}
+
+impl OtherDataFrame {
+    pub fn new<S: Into<String>>(name: S, df: DataFrame) -> Self {
+        Self {
+            name: name.into(),
+            df,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn into_inner(self) -> polars::prelude::DataFrame {
+        self.df
+    }
+}
+
+/// A record batch from one of the tables of a POD5 file, for when batches
+/// from different tables are returned together, such as by
+/// [`Follow::poll`](crate::follow::Follow::poll).
+#[derive(Debug, Clone, PartialEq)]
+pub enum TableBatch {
+    /// Signal table batch, with the signal decompressed.
+    Signal(SignalDataFrame),
+    Read(ReadDataFrame),
+    RunInfo(RunInfoDataFrame),
+    Other(OtherDataFrame),
+}
+
+/// Iterator over the record batches of any embedded table.
+///
+/// Batches are returned as they are stored, so the `signal` column of a
+/// Signal table is left compressed.
+pub struct EmbeddedTableIter<R: Read + Seek> {
+    pub(crate) fields: Vec<Field>,
+    pub(crate) table_reader: FileReader<TableWindow<R>>,
+}
+
+impl<R: Read + Seek> EmbeddedTableIter<R> {
+    pub(crate) fn new(offset: u64, length: u64, file: Arc<Mutex<R>>) -> Result<Self, Pod5Error> {
+        let (fields, table_reader) =
+            read_to_dataframe(offset, length, Pod5Error::InvalidTable(offset), file)?;
+        Ok(Self {
+            fields,
+            table_reader,
+        })
+    }
+
+    pub fn fields(&self) -> &[Field] {
+        self.fields.as_ref()
+    }
+
+    /// Name given to a user table when it was written, if any.
+    pub fn name(&self) -> Option<&str> {
+        self.table_reader
+            .metadata()
+            .custom_schema_metadata
+            .as_ref()?
+            .get(TABLE_NAME_KEY)
+            .map(PlSmallStr::as_str)
+    }
+}
+
+impl<R: Read + Seek> Iterator for EmbeddedTableIter<R> {
+    type Item = Result<DataFrame, Pod5Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        get_next_df(&self.fields, &mut self.table_reader)
+    }
+}
+
+/// Arrow schema metadata key holding the name of a user table.
+pub(crate) const TABLE_NAME_KEY: &str = "pod5-rs:table_name";
+
+/// Guess the type of a table from its schema when there's no footer to say
+/// what it is, along with the name of user tables.
+pub(crate) fn classify_table(
+    schema: &ArrowSchema,
+    metadata: Option<&Metadata>,
+) -> Option<(ContentType, Option<String>)> {
+    let name = metadata
+        .and_then(|m| m.get(TABLE_NAME_KEY))
+        .map(|name| name.to_string());
+    if name.is_some() {
+        return Some((ContentType::OtherIndex, name));
+    }
+    let has = |column: &str| schema.contains(column);
+    let content_type = if has("samples") {
+        ContentType::SignalTable
+    } else if has("read_table_row") {
+        ContentType::ReadIdIndex
+    } else if has("num_samples") {
+        ContentType::ReadsTable
+    } else if has("acquisition_id") {
+        ContentType::RunInfoTable
+    } else {
+        return None;
+    };
+    Some((content_type, None))
+}
+
+/// Custom schema metadata from the footer of a complete Arrow IPC file,
+/// which may be followed by padding.
+///
+/// polars-arrow only writes the custom metadata to the Arrow footer, so it's
+/// missing from the schema message at the start of the tables we write.
+pub(crate) fn arrow_footer_metadata(table: &[u8]) -> Option<Arc<Metadata>> {
+    let end = table.iter().rposition(|&b| b != 0)? + 1;
+    read_file_metadata(&mut Cursor::new(&table[..end]))
+        .ok()?
+        .custom_schema_metadata
+}
+
+/// DataFrame wrapper for the embedded read_id index table.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ReadIdIndexDataFrame(pub(crate) DataFrame);
+
+impl ReadIdIndexDataFrame {
+    pub fn into_inner(self) -> polars::prelude::DataFrame {
+        self.0
+    }
+}
+
+pub(crate) fn get_next_df<R: Read + Seek>(
     fields: &[Field],
-    table_reader: &mut FileReader<Cursor<Vec<u8>>>,
+    table_reader: &mut FileReader<R>,
 ) -> Option<Result<DataFrame, Pod5Error>> {
     // TODO: Remove unwrap and avoid Option since it
     // can hide conversion problems
     table_reader.next().map(|chunk| {
         chunk
-            .map(|batch| {
-                let mut acc = Vec::with_capacity(fields.len());
-                for (arr, f) in batch.into_arrays().into_iter().zip(fields.iter()) {
-                    let s = compatibility::array_to_series(f, arr);
-                    acc.push(s);
-                }
+            .map_err(Pod5Error::PolarsError)
+            .and_then(|batch| batch_to_df(fields, batch))
+    })
+}
 
-                polars::prelude::DataFrame::from_iter(acc)
-            })
+/// Same as [`get_next_df`] for the Read table, converting the record batches
+/// of files older than v3 with `legacy`.
+pub(crate) fn get_next_read_df<R: Read + Seek>(
+    fields: &[Field],
+    table_reader: &mut FileReader<R>,
+    legacy: Option<&LegacyReads>,
+) -> Option<Result<DataFrame, Pod5Error>> {
+    let Some(legacy) = legacy else {
+        return get_next_df(fields, table_reader);
+    };
+    table_reader.next().map(|chunk| {
+        chunk
             .map_err(Pod5Error::PolarsError)
+            .and_then(|batch| legacy.to_df(fields, batch))
     })
 }
 
-pub(crate) type TableReader = (Vec<Field>, FileReader<Cursor<Vec<u8>>>);
+/// Convert an Arrow record batch into a DataFrame, converting the POD5
+/// extension types into types polars can handle.
+pub(crate) fn batch_to_df(
+    fields: &[Field],
+    batch: RecordBatchT<Box<dyn Array>>,
+) -> Result<DataFrame, Pod5Error> {
+    let mut acc = Vec::with_capacity(fields.len());
+    for (arr, f) in batch.into_arrays().into_iter().zip(fields.iter()) {
+        let s = compatibility::array_to_series(f, arr)?;
+        acc.push(s);
+    }
+
+    Ok(polars::prelude::DataFrame::from_iter(acc))
+}
+
+pub(crate) type TableReader<R> = (Vec<Field>, FileReader<TableWindow<R>>);
 
+/// Open the Arrow IPC table embedded at `offset..offset + length`.
+///
+/// Only the Arrow footer is read here, record batches are read from the
+/// underlying reader as the returned `FileReader` is iterated.
 pub(crate) fn read_to_dataframe<R: Read + Seek>(
     offset: u64,
     length: u64,
     err: Pod5Error,
-    file: &mut R,
-) -> Result<TableReader, Pod5Error> {
-    let mut run_info_buf = vec![0u8; length as usize];
-    file.seek(SeekFrom::Start(offset))?;
-    file.read_exact(&mut run_info_buf)?;
-    let mut run_info_buf = Cursor::new(run_info_buf);
-    let metadata = read_file_metadata(&mut run_info_buf).map_err(|_| err)?;
-    let fields = metadata.schema.iter().map(|f| f.1).cloned().collect();
-
-    let signal_table = FileReader::new(run_info_buf, metadata, None, None);
-    Ok((fields, signal_table))
-}
-
-pub(crate) fn parse_uuid_from_read_id(
-    series: pl::Column,
-) -> Result<Option<pl::Column>, PolarsError> {
-    let read_ids = series
-        .binary()
-        .unwrap()
-        .into_iter()
-        .map(|bs: Option<&[u8]>| bs.map(|bbs| uuid::Uuid::from_slice(bbs).unwrap().to_string()))
-        .collect::<Vec<_>>();
-    Ok(Some(Column::from(Series::new(
-        series.name().clone(),
-        read_ids,
-    ))))
-}
-
-pub(crate) fn decompress_signal_series(
-    sample_signal: Column,
-) -> Result<Option<Column>, PolarsError> {
-    let sample_signal = sample_signal.struct_().unwrap().fields_as_series();
-    let sample = sample_signal[0].u32().unwrap();
-    let signal = sample_signal[1].binary().unwrap();
-    let out = sample
+    file: Arc<Mutex<R>>,
+) -> Result<TableReader<R>, Pod5Error> {
+    read_to_dataframe_projected(offset, length, err, file, None)
+}
+
+/// Same as [`read_to_dataframe`], but only the given `columns` are read from
+/// each record batch. Columns are returned in table order, regardless of the
+/// order of `columns`.
+pub(crate) fn read_to_dataframe_projected<R: Read + Seek>(
+    offset: u64,
+    length: u64,
+    err: Pod5Error,
+    file: Arc<Mutex<R>>,
+    columns: Option<&[PlSmallStr]>,
+) -> Result<TableReader<R>, Pod5Error> {
+    let mut window = TableWindow::new(file, offset, length);
+    let metadata = read_file_metadata(&mut window).map_err(|_| err)?;
+    let projection = columns
+        .map(|columns| {
+            columns
+                .iter()
+                .map(|name| {
+                    metadata
+                        .schema
+                        .index_of(name)
+                        .ok_or_else(|| PolarsError::ColumnNotFound(name.to_string().into()))
+                })
+                .collect::<Result<Vec<_>, _>>()
+        })
+        .transpose()?
+        .map(|mut projection| {
+            projection.sort_unstable();
+            projection.dedup();
+            projection
+        });
+
+    let table_reader = FileReader::new(window, metadata, projection, None);
+    let fields = table_reader.schema().iter().map(|f| f.1).cloned().collect();
+    Ok((fields, table_reader))
+}
+
+/// Convert a column of binary UUIDs into strings.
+pub(crate) fn parse_uuid_from_read_id(column: &Column) -> Result<Column, Pod5Error> {
+    let read_ids = column
+        .binary()?
         .into_iter()
-        .zip(signal)
-        .map(|(sa, si)| {
-            let sa = sa.unwrap();
-            let si = si.unwrap(); // Vec<u8>
-            let decoded = decode(si, sa as usize).unwrap();
-            Series::from_iter(decoded)
+        .enumerate()
+        .map(|(row, bytes)| {
+            bytes
+                .map(|bytes| {
+                    uuid::Uuid::from_slice(bytes)
+                        .map(|uuid| uuid.to_string())
+                        .map_err(|e| Pod5Error::InvalidReadId {
+                            row,
+                            reason: e.to_string(),
+                        })
+                })
+                .transpose()
         })
-        .collect::<Vec<_>>();
-    Ok(Some(Column::from(Series::new("decompressed".into(), out))))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(Column::from(Series::new(column.name().clone(), read_ids)))
+}
+
+/// Decode the compressed `signal` column of a Signal table DataFrame using
+/// the `samples` column, `signal_row` mapping a row of the DataFrame to its
+/// Signal table row for errors.
+pub(crate) fn decompress_signal_column<F>(
+    df: &DataFrame,
+    signal_row: F,
+    corrupt: Option<&CorruptSignalLog>,
+) -> Result<Column, Pod5Error>
+where
+    F: Fn(usize) -> u64,
+{
+    let samples = df.column("samples")?.u32()?;
+    let signal = df.column("signal")?.binary()?;
+    let read_ids = df.column("read_id").ok().and_then(|c| c.str().ok());
+    let mut out = Vec::with_capacity(df.height());
+    for (row, (samples, signal)) in samples.into_iter().zip(signal).enumerate() {
+        let decoded = match (samples, signal) {
+            (Some(samples), Some(signal)) => {
+                decode(signal, samples as usize).map_err(|e| e.to_string())
+            }
+            (None, _) => Err("missing sample count".to_string()),
+            (_, None) => Err("missing signal".to_string()),
+        };
+        let reason = match decoded {
+            Ok(decoded) => {
+                out.push(Some(Series::from_iter(decoded)));
+                continue;
+            }
+            Err(reason) => reason,
+        };
+        let failure = CorruptSignalRow {
+            read_id: read_ids.and_then(|ids| ids.get(row)).map(String::from),
+            row: signal_row(row),
+            reason,
+        };
+        match corrupt {
+            Some(log) => {
+                log.record(failure);
+                out.push(None);
+            }
+            None => return Err(failure.into()),
+        }
+    }
+    let signal = Series::new("signal".into(), out)
+        .cast(&pl::DataType::List(Box::new(pl::DataType::Int16)))?;
+    Ok(Column::from(signal))
+}
+
+/// A Signal table row whose compressed signal couldn't be decoded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CorruptSignalRow {
+    read_id: Option<String>,
+    row: u64,
+    reason: String,
+}
+
+impl CorruptSignalRow {
+    /// The read the row belongs to, if its read_id could be read.
+    pub fn read_id(&self) -> Option<&str> {
+        self.read_id.as_deref()
+    }
+
+    /// Row of the Signal table.
+    pub fn row(&self) -> u64 {
+        self.row
+    }
+
+    pub fn reason(&self) -> &str {
+        &self.reason
+    }
+}
+
+impl From<CorruptSignalRow> for Pod5Error {
+    fn from(corrupt: CorruptSignalRow) -> Self {
+        Pod5Error::SignalDecodeFailure {
+            read_id: corrupt.read_id,
+            row: corrupt.row,
+            reason: corrupt.reason,
+        }
+    }
+}
+
+/// Signal table rows that failed to decode, shared between a
+/// [`Reader`](crate::reader::Reader) and the signal iterators created from
+/// it. Keyed by row so that rows decoded more than once are only reported
+/// once.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct CorruptSignalLog(Arc<Mutex<BTreeMap<u64, CorruptSignalRow>>>);
+
+impl CorruptSignalLog {
+    fn record(&self, corrupt: CorruptSignalRow) {
+        let mut rows = self.0.lock().expect("corrupt signal log poisoned");
+        rows.insert(corrupt.row, corrupt);
+    }
+
+    pub(crate) fn report(&self) -> CorruptSignalReport {
+        let rows = self.0.lock().expect("corrupt signal log poisoned");
+        CorruptSignalReport {
+            rows: rows.values().cloned().collect(),
+        }
+    }
+}
+
+/// Every corrupt Signal table row skipped so far, see
+/// [`Reader::skip_corrupt_signal`](crate::reader::Reader::skip_corrupt_signal).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CorruptSignalReport {
+    rows: Vec<CorruptSignalRow>,
+}
+
+impl CorruptSignalReport {
+    /// Whether every row read so far was decoded.
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+
+    /// The corrupt rows, in Signal table order.
+    pub fn rows(&self) -> &[CorruptSignalRow] {
+        &self.rows
+    }
+
+    /// The corrupt rows of each read. Rows without a read_id are left out.
+    pub fn by_read(&self) -> BTreeMap<&str, Vec<&CorruptSignalRow>> {
+        let mut reads = BTreeMap::<_, Vec<_>>::new();
+        for corrupt in &self.rows {
+            if let Some(read_id) = corrupt.read_id() {
+                reads.entry(read_id).or_default().push(corrupt);
+            }
+        }
+        reads
+    }
 }
 
 #[derive(Debug)]
@@ -321,30 +945,55 @@ pub(crate) struct AdcData {
 pub struct Calibration(pub(crate) HashMap<String, AdcData>);
 
 impl Calibration {
-    fn from_read_dfs(iter: ReadDataFrameIter) -> Self {
-        let mut cal_data = HashMap::new();
-        for read_df in iter.flatten() {
-            let df = read_df
-                .0
-                .select(["read_id", "calibration_offset", "calibration_scale"])
-                .unwrap();
-            let iters = df.iter().collect::<Vec<_>>();
-            for (read_id, offset, scale) in itertools::multizip((
-                iters[0].str().unwrap().into_iter().flatten(),
-                iters[1].f32().unwrap().into_iter().flatten(),
-                iters[2].f32().unwrap().into_iter().flatten(),
-            )) {
-                cal_data.insert(read_id.to_string(), AdcData { offset, scale });
+    fn from_read_dfs<R: Read + Seek>(iter: ReadDataFrameIter<R>) -> Result<Self, Pod5Error> {
+        let mut calibration = Calibration(HashMap::new());
+        for read_df in iter {
+            calibration.extend(&read_df?.0)?;
+        }
+        Ok(calibration)
+    }
+
+    /// Add the calibration of every read in a Read table DataFrame. Reads
+    /// with a null offset or scale are left out, so converting their signal
+    /// returns [`Pod5Error::CalibrationMissing`].
+    fn extend(&mut self, df: &DataFrame) -> Result<(), Pod5Error> {
+        let df = df.select(["read_id", "calibration_offset", "calibration_scale"])?;
+        let iters = df.iter().collect::<Vec<_>>();
+        for row in itertools::multizip((
+            iters[0].str()?.into_iter(),
+            iters[1].f32()?.into_iter(),
+            iters[2].f32()?.into_iter(),
+        )) {
+            if let (Some(read_id), Some(offset), Some(scale)) = row {
+                self.0
+                    .insert(read_id.to_string(), AdcData { offset, scale });
             }
         }
-        Calibration(cal_data)
+        Ok(())
+    }
+
+    /// Calibration of the read in each row of the `read_id` column.
+    fn for_reads(&self, df: &DataFrame) -> Result<Vec<&AdcData>, Pod5Error> {
+        df.column("read_id")?
+            .str()?
+            .into_iter()
+            .map(|read_id| {
+                let read_id = read_id.unwrap_or_default();
+                self.0
+                    .get(read_id)
+                    .ok_or_else(|| Pod5Error::CalibrationMissing(read_id.to_string()))
+            })
+            .collect()
     }
 }
 
 #[cfg(test)]
 mod test {
-    use std::fs::File;
+    use std::{collections::HashMap, fs::File};
+
+    use polars::{df, prelude::NamedFrom, series::Series};
 
+    use super::*;
     use crate::reader::Reader;
 
     #[test]
@@ -357,10 +1006,10 @@ mod test {
             let df = read_df.0.head(Some(4));
             println!("{df:?}");
         }
-        let cal = reader.read_dfs()?.into_calibration();
+        let cal = reader.read_dfs()?.into_calibration()?;
 
         for signal_df in reader.signal_dfs()?.flatten() {
-            let df = signal_df.to_picoamps(&cal).0.head(Some(4));
+            let df = signal_df.to_picoamps(&cal)?.0.head(Some(4));
             println!("{df:?}");
         }
         for run_info_df in reader.run_info_dfs()?.flatten() {
@@ -369,7 +1018,54 @@ mod test {
         }
 
         let signal_df = reader.signal_dfs()?.flatten().next().unwrap();
-        println!("{:?}", signal_df.to_picoamps(&cal));
+        println!("{:?}", signal_df.to_picoamps(&cal)?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_signal_errors() -> eyre::Result<()> {
+        let signal = Series::new(
+            "signal".into(),
+            [
+                svb16::encode(&[1, 2, 3])?,
+                svb16::encode(&[4, 5, 6])?[..4].to_vec(),
+            ],
+        );
+        let df = df!(
+            "read_id" => ["a", "b"],
+            "samples" => [3u32, 3],
+            "signal" => signal,
+        )?;
+        let err = decompress_signal_column(&df, |row| row as u64 + 10, None).unwrap_err();
+        assert!(matches!(
+            err,
+            Pod5Error::SignalDecodeFailure { read_id: Some(ref id), row: 11, .. } if id == "b"
+        ));
+
+        let err = SignalDataFrame(df.head(Some(1)))
+            .to_picoamps(&Calibration(HashMap::new()))
+            .unwrap_err();
+        assert!(matches!(err, Pod5Error::CompressedSignal));
+
+        // A null calibration doesn't shift later reads onto the wrong read_id
+        let reads = df!(
+            "read_id" => ["a", "b", "c"],
+            "calibration_offset" => [Some(1.0f32), None, Some(3.0)],
+            "calibration_scale" => [Some(0.5f32), Some(0.25), Some(0.125)],
+        )?;
+        let mut calibration = Calibration(HashMap::new());
+        calibration.extend(&reads)?;
+        assert_eq!(calibration.0.len(), 2);
+        assert_eq!(calibration.0["c"].offset, 3.0);
+        assert_eq!(calibration.0["c"].scale, 0.125);
+        let err = calibration.for_reads(&reads).unwrap_err();
+        assert!(matches!(err, Pod5Error::CalibrationMissing(ref id) if id == "b"));
+
+        let signal_df = SignalDataFrame(df.head(Some(1))).decompress_signal()?;
+        let err = signal_df
+            .to_picoamps(&Calibration(HashMap::new()))
+            .unwrap_err();
+        assert!(matches!(err, Pod5Error::CalibrationMissing(ref id) if id == "a"));
         Ok(())
     }
 }
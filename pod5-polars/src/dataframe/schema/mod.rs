@@ -3,6 +3,8 @@ use std::sync::Arc;
 use polars::prelude::{ArrowDataType, ArrowField, PlSmallStr};
 use polars_arrow::datatypes::{ArrowSchemaRef, IntegerType, Metadata};
 
+pub mod read_id_index_schema;
+pub mod other_schema;
 pub mod reads_schema;
 pub mod run_info_schema;
 pub mod signal_schema;
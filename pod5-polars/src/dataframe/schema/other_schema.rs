@@ -0,0 +1,19 @@
+use std::sync::Arc;
+
+use polars_arrow::datatypes::{ArrowSchema, ArrowSchemaRef};
+
+use super::TableSchema;
+
+/// Placeholder schema for user tables embedded as `OtherIndex`.
+///
+/// User tables don't have a fixed schema, so this is empty and the schema
+/// written to the file is taken from the first batch passed to
+/// [`TableWriteGuard::write_batch`](crate::writer::TableWriteGuard::write_batch).
+#[derive(Debug, Clone)]
+pub struct OtherSchema;
+
+impl TableSchema for OtherSchema {
+    fn as_schema() -> ArrowSchemaRef {
+        Arc::new(ArrowSchema::default())
+    }
+}
@@ -0,0 +1,35 @@
+use std::sync::Arc;
+
+use polars_arrow::datatypes::{ArrowDataType, ArrowSchemaRef};
+
+use super::{TableSchema, name_field, signal_schema::read_id};
+
+/// Schema of the embedded read_id index table.
+///
+/// The POD5 specification reserves the `ReadIdIndex` content type without
+/// defining a layout, so this one maps each `read_id`, sorted by its UUID
+/// bytes, to the row of the Read table containing it.
+#[derive(Debug, Clone)]
+pub struct ReadIdIndexSchema {
+    inner: ArrowSchemaRef,
+}
+
+impl TableSchema for ReadIdIndexSchema {
+    fn as_schema() -> ArrowSchemaRef {
+        Self::new().inner
+    }
+}
+
+impl ReadIdIndexSchema {
+    pub fn new() -> Self {
+        let inner = Arc::new(polars_arrow::datatypes::ArrowSchema::from_iter([
+            read_id(),
+            name_field("read_table_row", ArrowDataType::UInt64),
+        ]));
+        Self { inner }
+    }
+
+    pub fn into_inner(self) -> ArrowSchemaRef {
+        self.inner
+    }
+}
@@ -76,13 +76,16 @@ mod test {
 
     #[test]
     fn test_signal_schema() {
-        let path = "../extra/multi_fast5_zip_v3.pod5";
-        let mut file = File::open(path).unwrap();
-        let mut reader = Reader::from_reader(&mut file).unwrap();
-        let signal_df_iter = reader.signal_dfs().unwrap();
-        pretty_assertions::assert_eq!(
-            *SignalSchema::new().into_inner(),
-            signal_df_iter.table_reader.schema().clone()
-        );
+        // The Signal table hasn't changed since v0
+        for version in 0..4 {
+            let path = format!("../extra/multi_fast5_zip_v{version}.pod5");
+            let mut file = File::open(path).unwrap();
+            let mut reader = Reader::from_reader(&mut file).unwrap();
+            let signal_df_iter = reader.signal_dfs().unwrap();
+            pretty_assertions::assert_eq!(
+                *SignalSchema::new().into_inner(),
+                signal_df_iter.table_reader.schema().clone()
+            );
+        }
     }
 }
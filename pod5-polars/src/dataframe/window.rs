@@ -0,0 +1,115 @@
+//! Bounded views into the tables embedded in a POD5 file.
+use std::{
+    io::{self, Read, Seek, SeekFrom},
+    sync::{Arc, Mutex},
+};
+
+/// Reader over a single embedded table in a POD5 file.
+///
+/// The Arrow IPC reader needs to seek relative to the start and end of the
+/// table, so this restricts the underlying reader to the `offset..offset +
+/// length` byte range given by the footer's `TableInfo`. Record batches are
+/// then read from disk on demand instead of copying the whole table into
+/// memory first.
+///
+/// The underlying reader is shared between every table of a
+/// [`Reader`](crate::reader::Reader), so each read seeks to the window's own
+/// position before reading.
+#[derive(Debug)]
+pub struct TableWindow<R> {
+    inner: Arc<Mutex<R>>,
+    offset: u64,
+    length: u64,
+    position: u64,
+}
+
+impl<R> TableWindow<R> {
+    pub(crate) fn new(inner: Arc<Mutex<R>>, offset: u64, length: u64) -> Self {
+        Self {
+            inner,
+            offset,
+            length,
+            position: 0,
+        }
+    }
+
+    /// Length of the table in bytes.
+    pub fn len(&self) -> u64 {
+        self.length
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.length == 0
+    }
+}
+
+impl<R: Read + Seek> Read for TableWindow<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let remaining = self.length.saturating_sub(self.position);
+        let max = buf.len().min(remaining as usize);
+        if max == 0 {
+            return Ok(0);
+        }
+        let mut inner = self
+            .inner
+            .lock()
+            .map_err(|_| io::Error::other("POD5 reader lock poisoned"))?;
+        inner.seek(SeekFrom::Start(self.offset + self.position))?;
+        let n = inner.read(&mut buf[..max])?;
+        self.position += n as u64;
+        Ok(n)
+    }
+}
+
+impl<R> Seek for TableWindow<R> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let new_position = match pos {
+            SeekFrom::Start(n) => Some(n),
+            SeekFrom::End(n) => self.length.checked_add_signed(n),
+            SeekFrom::Current(n) => self.position.checked_add_signed(n),
+        };
+        match new_position {
+            Some(n) => {
+                self.position = n;
+                Ok(n)
+            }
+            None => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "invalid seek to a negative or overflowing position",
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::Cursor;
+
+    use super::*;
+
+    #[test]
+    fn test_window() {
+        let inner = Arc::new(Mutex::new(Cursor::new((0u8..32).collect::<Vec<_>>())));
+        let mut window = TableWindow::new(inner.clone(), 8, 8);
+        let mut other = TableWindow::new(inner, 24, 4);
+
+        let mut buf = Vec::new();
+        window.read_to_end(&mut buf).unwrap();
+        assert_eq!(buf, (8..16).collect::<Vec<_>>());
+
+        window.seek(SeekFrom::End(-2)).unwrap();
+        let mut buf = [0u8; 2];
+        window.read_exact(&mut buf).unwrap();
+        assert_eq!(buf, [14, 15]);
+
+        // Windows keep their own position despite sharing the reader
+        other.read_exact(&mut buf).unwrap();
+        window.seek(SeekFrom::Start(0)).unwrap();
+        window.read_exact(&mut buf).unwrap();
+        assert_eq!(buf, [8, 9]);
+        other.read_exact(&mut buf).unwrap();
+        assert_eq!(buf, [26, 27]);
+
+        assert!(window.seek(SeekFrom::Current(-10)).is_err());
+    }
+}
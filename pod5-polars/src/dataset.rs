@@ -0,0 +1,326 @@
+//! Reading many POD5 files as a single dataset.
+//!
+//! A sequencing run usually produces many POD5 files. [`Dataset`] opens each
+//! of them in turn and exposes their tables as one stream of DataFrames, with
+//! a `source_file` column recording which file each row came from.
+use std::{
+    fs::File,
+    num::NonZeroUsize,
+    path::{Path, PathBuf},
+    thread,
+};
+
+use polars::{
+    frame::DataFrame,
+    prelude::{AnyValue, Column, DataType, LazyFrame, Scalar, UnionArgs, concat, lit},
+};
+
+use crate::{
+    dataframe::{ReadDataFrame, RunInfoDataFrame, SignalDataFrame},
+    error::Pod5Error,
+    reader::Reader,
+    scan::scan_pod5,
+};
+
+/// Name of the column added to every DataFrame with the path of the file
+/// the row was read from.
+pub const SOURCE_FILE: &str = "source_file";
+
+/// A collection of POD5 files treated as one dataset.
+#[derive(Debug, Clone)]
+pub struct Dataset {
+    paths: Vec<PathBuf>,
+    threads: usize,
+}
+
+impl Dataset {
+    /// Create a dataset from a list of POD5 file paths.
+    pub fn new<I, P>(paths: I) -> Self
+    where
+        I: IntoIterator<Item = P>,
+        P: Into<PathBuf>,
+    {
+        Self {
+            paths: paths.into_iter().map(Into::into).collect(),
+            threads: 1,
+        }
+    }
+
+    /// Create a dataset from every file matching a glob pattern, such as
+    /// `"run/pod5/*.pod5"`. Files are sorted by path.
+    pub fn from_glob(pattern: &str) -> Result<Self, Pod5Error> {
+        let mut paths = glob::glob(pattern)?
+            .map(|path| path.map_err(std::io::Error::from))
+            .collect::<Result<Vec<_>, _>>()?;
+        paths.sort();
+        Ok(Self::new(paths))
+    }
+
+    /// Number of threads used by [`Dataset::map_files`] to process files in
+    /// parallel. Defaults to 1.
+    pub fn with_threads(mut self, threads: usize) -> Self {
+        self.threads = threads.max(1);
+        self
+    }
+
+    /// Use as many threads as there are available CPUs.
+    pub fn parallel(self) -> Self {
+        let threads = thread::available_parallelism().map_or(1, NonZeroUsize::get);
+        self.with_threads(threads)
+    }
+
+    pub fn paths(&self) -> &[PathBuf] {
+        &self.paths
+    }
+
+    /// Iterate over the Read tables of every file.
+    pub fn read_dfs(&self) -> DatasetIter<ReadDataFrame> {
+        DatasetIter::new(self.paths.clone(), |reader| {
+            Ok(Box::new(reader.read_dfs()?.map(|df| df.map(|df| df.0))))
+        })
+    }
+
+    /// Iterate over the Signal tables of every file.
+    pub fn signal_dfs(&self) -> DatasetIter<SignalDataFrame> {
+        DatasetIter::new(self.paths.clone(), |reader| {
+            Ok(Box::new(reader.signal_dfs()?.map(|df| df.map(|df| df.0))))
+        })
+    }
+
+    /// Iterate over the Run Info tables of every file.
+    ///
+    /// Files from the same run usually share the same run info, so rows that
+    /// are identical to a row from an earlier file are skipped.
+    pub fn run_info_dfs(&self) -> impl Iterator<Item = Result<RunInfoDataFrame, Pod5Error>> {
+        let mut seen: Vec<DataFrame> = Vec::new();
+        DatasetIter::<RunInfoDataFrame>::new(self.paths.clone(), |reader| {
+            Ok(Box::new(reader.run_info_dfs()?.map(|df| df.map(|df| df.0))))
+        })
+        .filter_map(move |df| {
+            let df = match df {
+                Ok(df) => df.0,
+                Err(e) => return Some(Err(e)),
+            };
+            let mut keep = Vec::with_capacity(df.height());
+            for row in 0..df.height() {
+                let row_df = df.slice(row as i64, 1);
+                let run_info = match row_df.drop(SOURCE_FILE) {
+                    Ok(run_info) => run_info,
+                    Err(e) => return Some(Err(e.into())),
+                };
+                if !seen.iter().any(|s| s.equals_missing(&run_info)) {
+                    seen.push(run_info);
+                    keep.push(row_df);
+                }
+            }
+            let mut keep = keep.into_iter();
+            let mut acc = keep.next()?;
+            for row_df in keep {
+                if let Err(e) = acc.vstack_mut(&row_df) {
+                    return Some(Err(e.into()));
+                }
+            }
+            Some(Ok(RunInfoDataFrame(acc)))
+        })
+    }
+
+    /// Lazily scan the reads of every file, see
+    /// [`scan_pod5`](crate::scan::scan_pod5).
+    pub fn scan(&self) -> Result<LazyFrame, Pod5Error> {
+        let lfs = self
+            .paths
+            .iter()
+            .map(|path| Ok(scan_pod5(path)?.with_column(lit(path_str(path)).alias(SOURCE_FILE))))
+            .collect::<Result<Vec<_>, Pod5Error>>()?;
+        Ok(concat(lfs, UnionArgs::default())?)
+    }
+
+    /// Run `f` on a [`Reader`] for every file, returning the results in the
+    /// same order as the files.
+    ///
+    /// Files are processed in parallel if the dataset was configured with more
+    /// than one thread.
+    pub fn map_files<T, F>(&self, f: F) -> Vec<Result<T, Pod5Error>>
+    where
+        T: Send,
+        F: Fn(&Path, Reader<File>) -> Result<T, Pod5Error> + Sync,
+    {
+        let open_and_run = |path: &PathBuf| {
+            let reader = Reader::from_reader(File::open(path)?)?;
+            f(path, reader)
+        };
+        if self.threads <= 1 {
+            return self.paths.iter().map(open_and_run).collect();
+        }
+
+        let chunk_size = self.paths.len().div_ceil(self.threads).max(1);
+        thread::scope(|scope| {
+            let handles = self
+                .paths
+                .chunks(chunk_size)
+                .map(|paths| scope.spawn(|| paths.iter().map(open_and_run).collect::<Vec<_>>()))
+                .collect::<Vec<_>>();
+            handles
+                .into_iter()
+                .flat_map(|handle| handle.join().expect("dataset worker thread panicked"))
+                .collect()
+        })
+    }
+
+    /// Read every file's Read table into a single DataFrame.
+    ///
+    /// Uses [`Dataset::map_files`], so files are read in parallel if
+    /// configured.
+    pub fn collect_reads(&self) -> Result<ReadDataFrame, Pod5Error> {
+        let dfs = self.map_files(|path, mut reader| {
+            let mut acc = DataFrame::empty();
+            for df in reader.read_dfs()? {
+                acc.vstack_mut(&with_source_file(df?.0, path)?)?;
+            }
+            Ok(acc)
+        });
+        let mut acc = DataFrame::empty();
+        for df in dfs {
+            acc.vstack_mut(&df?)?;
+        }
+        Ok(ReadDataFrame(acc))
+    }
+}
+
+/// Iterator over one of the tables of every file in a [`Dataset`].
+pub struct DatasetIter<T> {
+    paths: std::vec::IntoIter<PathBuf>,
+    open: OpenTable,
+    current: Option<(PathBuf, TableIter)>,
+    table: std::marker::PhantomData<T>,
+}
+
+type TableIter = Box<dyn Iterator<Item = Result<DataFrame, Pod5Error>> + Send>;
+type OpenTable = fn(&mut Reader<File>) -> Result<TableIter, Pod5Error>;
+
+impl<T> DatasetIter<T> {
+    fn new(paths: Vec<PathBuf>, open: OpenTable) -> Self {
+        Self {
+            paths: paths.into_iter(),
+            open,
+            current: None,
+            table: std::marker::PhantomData,
+        }
+    }
+
+    fn next_df(&mut self) -> Option<Result<DataFrame, Pod5Error>> {
+        loop {
+            if let Some((path, iter)) = self.current.as_mut() {
+                match iter.next() {
+                    Some(df) => return Some(df.and_then(|df| with_source_file(df, path))),
+                    None => self.current = None,
+                }
+            }
+            let path = self.paths.next()?;
+            let opened = File::open(&path)
+                .map_err(Pod5Error::from)
+                .and_then(Reader::from_reader)
+                .and_then(|mut reader| (self.open)(&mut reader));
+            match opened {
+                Ok(iter) => self.current = Some((path, iter)),
+                Err(e) => return Some(Err(e)),
+            }
+        }
+    }
+}
+
+macro_rules! dataset_iter {
+    ($frame:ident) => {
+        impl Iterator for DatasetIter<$frame> {
+            type Item = Result<$frame, Pod5Error>;
+
+            fn next(&mut self) -> Option<Self::Item> {
+                self.next_df().map(|df| df.map($frame))
+            }
+        }
+    };
+}
+
+dataset_iter!(ReadDataFrame);
+dataset_iter!(SignalDataFrame);
+dataset_iter!(RunInfoDataFrame);
+
+fn path_str(path: &Path) -> String {
+    path.to_string_lossy().into_owned()
+}
+
+fn with_source_file(mut df: DataFrame, path: &Path) -> Result<DataFrame, Pod5Error> {
+    let source = Column::new_scalar(
+        SOURCE_FILE.into(),
+        Scalar::new(
+            DataType::String,
+            AnyValue::StringOwned(path_str(path).into()),
+        ),
+        df.height(),
+    );
+    df.with_column(source)?;
+    Ok(df)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const V3: &str = "../extra/multi_fast5_zip_v3.pod5";
+
+    #[test]
+    fn test_dataset_iters() -> eyre::Result<()> {
+        let dataset = Dataset::new([V3, V3]);
+        let mut reader = Reader::from_reader(File::open(V3)?)?;
+        let num_reads = reader
+            .read_dfs()?
+            .flatten()
+            .map(|df| df.0.height())
+            .sum::<usize>();
+
+        let mut rows = 0;
+        for df in dataset.read_dfs() {
+            let df = df?.0;
+            let source = df.column(SOURCE_FILE)?.str()?.get(0).map(String::from);
+            assert_eq!(source.as_deref(), Some(V3));
+            rows += df.height();
+        }
+        assert_eq!(rows, 2 * num_reads);
+
+        let signal_rows = dataset
+            .signal_dfs()
+            .map(|df| df.map(|df| df.0.height()))
+            .sum::<Result<usize, _>>()?;
+        assert!(signal_rows >= rows);
+
+        // Both files share the same run info
+        let run_infos = dataset.run_info_dfs().collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(run_infos.len(), 1);
+        assert_eq!(run_infos[0].0.height(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_dataset_parallel() -> eyre::Result<()> {
+        let serial = Dataset::new([V3, V3, V3]).collect_reads()?;
+        let parallel = Dataset::new([V3, V3, V3]).with_threads(2).collect_reads()?;
+        assert_eq!(serial, parallel);
+
+        let scanned = Dataset::new([V3, V3, V3]).scan()?.collect()?;
+        assert_eq!(scanned.height(), serial.0.height());
+
+        // Files older than v3 scan with the same schema
+        let v0 = "../extra/multi_fast5_zip_v0.pod5";
+        let mixed = Dataset::new([v0, V3]).scan()?.collect()?;
+        assert_eq!(mixed.height(), serial.0.height() / 3 * 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_dataset_glob() -> eyre::Result<()> {
+        let dataset = Dataset::from_glob("../extra/*_v3.pod5")?;
+        assert_eq!(dataset.paths(), [PathBuf::from(V3)]);
+        assert!(Dataset::from_glob("[").is_err());
+        Ok(())
+    }
+}
@@ -1,5 +1,5 @@
 //! Error types.
-use std::io;
+use std::{io, path::PathBuf};
 
 use polars::error::PolarsError;
 
@@ -31,9 +31,72 @@ pub enum Pod5Error {
     #[error("Missing Run Info table from POD5")]
     RunInfoTableMissing,
 
+    /// No user table with the requested name is embedded in the POD5.
+    #[error("Missing table from POD5: {0}")]
+    OtherTableMissing(String),
+
+    /// The embedded file at the given offset isn't a valid Arrow IPC table.
+    #[error("Embedded file at offset {0} isn't a valid Arrow table")]
+    InvalidTable(u64),
+
+    /// The requested read_id isn't present in the Read table.
+    #[error("Read not found in POD5: {0}")]
+    ReadNotFound(String),
+
+    /// A Signal table row index, usually from the Read table's `signal`
+    /// column, points past the end of the Signal table.
+    #[error("Signal row {0} out of bounds for Signal table with {1} rows")]
+    SignalRowOutOfBounds(u64, u64),
+
+    /// A Read table row index, usually from a read_id index, points past the
+    /// end of the Read table.
+    #[error("Read row {0} out of bounds for Read table with {1} rows")]
+    ReadRowOutOfBounds(u64, u64),
+
+    /// A column's Arrow data type can't be converted into a polars type.
+    #[error("Unsupported data type for column {column}: {dtype}")]
+    UnsupportedDataType { column: String, dtype: String },
+
+    /// A read_id isn't a valid 16 byte UUID.
+    #[error("Invalid read_id at row {row}: {reason}")]
+    InvalidReadId { row: usize, reason: String },
+
+    /// The compressed signal of a Signal table row couldn't be decoded.
+    #[error("Failed to decode signal at Signal table row {row} (read {read_id:?}): {reason}")]
+    SignalDecodeFailure {
+        read_id: Option<String>,
+        row: u64,
+        reason: String,
+    },
+
+    /// The signal of a DataFrame read in raw mode is still VBZ compressed,
+    /// so it can't be converted until it is decompressed.
+    #[error("Signal is still VBZ compressed, decompress it first")]
+    CompressedSignal,
+
+    /// No calibration was found for a read when converting its signal.
+    #[error("Missing calibration for read: {0}")]
+    CalibrationMissing(String),
+
+    /// Pattern for finding POD5 files isn't a valid glob
+    #[error("Invalid glob pattern: {0}")]
+    GlobPatternError(#[from] glob::PatternError),
+
+    /// A file covered by a read_id index has been rewritten since the index
+    /// was built.
+    #[error("Index is out of date for POD5 file: {0}")]
+    StaleIndex(PathBuf),
+
+    /// A read_id index sidecar file couldn't be parsed
+    #[error("Invalid read_id index: {0}")]
+    InvalidIndex(&'static str),
+
     #[error("Problem with reading metadata: {0}")]
     ReadMetadataError(PolarsError),
 
+    #[error("{0}")]
+    WriteError(#[from] crate::writer::WriteError),
+
     /// Error occured in the DataFrame API from polars
     #[error("{0}")]
     PolarsError(#[from] polars::prelude::PolarsError),
@@ -0,0 +1,548 @@
+//! Reading POD5 files that are still being written.
+//!
+//! While a POD5 file is being written it has no footer, so the tables can't
+//! be found with [`Reader`]. [`Follow`] instead walks the file from the start,
+//! finding each table after the section marker before it, and returns the
+//! record batches that have been completely written since the last call to
+//! [`Follow::poll`]. Once the footer has been written the file can be opened
+//! as a normal [`Reader`] with [`Follow::into_reader`].
+use std::{
+    collections::VecDeque,
+    io::{self, Read, Seek, SeekFrom},
+    sync::{Arc, Mutex},
+};
+
+use pod5_format::{
+    FOOTER_MAGIC, ParsedFooter, footer_generated::minknow::reads_format::ContentType,
+};
+use polars::frame::DataFrame;
+use polars_arrow::{
+    datatypes::Field,
+    io::ipc::{
+        format::ipc::{MessageRef, planus::ReadAsRoot},
+        read::{StreamReader, StreamState, read_stream_metadata},
+    },
+};
+
+use crate::{
+    FILE_SIGNATURE,
+    dataframe::{
+        CorruptSignalLog, CorruptSignalReport, OtherDataFrame, ReadDataFrame, RunInfoDataFrame,
+        SignalDataFrame, TableBatch, arrow_footer_metadata, batch_to_df, classify_table,
+    },
+    error::Pod5Error,
+    reader::Reader,
+};
+
+const SECTION_MARKER_LEN: u64 = 16;
+/// Arrow IPC file magic, padded to 8 bytes
+const ARROW_MAGIC: [u8; 8] = *b"ARROW1\0\0";
+const CONTINUATION_MARKER: [u8; 4] = [0xff; 4];
+
+/// Follows a POD5 file as it's being written, see the [module
+/// docs](crate::follow).
+///
+/// ```
+/// # use std::fs::File;
+/// # use pod5_polars::{dataframe::TableBatch, reader::Reader};
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let mut follow = Reader::follow(File::open("../extra/multi_fast5_zip_v3.pod5")?);
+/// let mut reads = 0;
+/// while !follow.is_finished() {
+///     for batch in follow.poll()? {
+///         if let TableBatch::Read(df) = batch {
+///             reads += df.into_inner().height();
+///         }
+///     }
+///     // Wait for more data to be written
+///     # break;
+/// }
+/// let mut reader = follow.into_reader()?;
+/// assert_eq!(reader.reads()?.count(), reads);
+/// # Ok(())
+/// # }
+/// ```
+pub struct Follow<R> {
+    reader: R,
+    section_marker: Option<[u8; SECTION_MARKER_LEN as usize]>,
+    /// Offset of the next table, or the footer, once the previous table has
+    /// been fully read.
+    next_table: u64,
+    table: Option<FollowedTable>,
+    finished: bool,
+    /// Set when skipping corrupt signal rows instead of returning an error.
+    corrupt_signal: Option<CorruptSignalLog>,
+}
+
+impl<R: Read + Seek> Follow<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            section_marker: None,
+            next_table: 0,
+            table: None,
+            finished: false,
+            corrupt_signal: None,
+        }
+    }
+
+    /// Give Signal table rows with corrupt signal null signal instead of
+    /// returning [`Pod5Error::SignalDecodeFailure`], as with
+    /// [`Reader::skip_corrupt_signal`]. The [`Reader`] from
+    /// [`Follow::into_reader`] keeps skipping them.
+    pub fn skip_corrupt_signal(mut self) -> Self {
+        self.corrupt_signal.get_or_insert_default();
+        self
+    }
+
+    /// Every corrupt signal row skipped so far. Always empty unless
+    /// [`Follow::skip_corrupt_signal`] was used.
+    pub fn corrupt_signal_report(&self) -> CorruptSignalReport {
+        self.corrupt_signal
+            .as_ref()
+            .map(CorruptSignalLog::report)
+            .unwrap_or_default()
+    }
+
+    /// Whether the footer has been written, so no more batches will be
+    /// added.
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    /// Read every record batch that has been completely written since the
+    /// last call, in the order they were written.
+    ///
+    /// Batches from the read_id index table and tables that can't be
+    /// identified are skipped.
+    pub fn poll(&mut self) -> Result<Vec<TableBatch>, Pod5Error> {
+        let mut batches = Vec::new();
+        if self.finished {
+            return Ok(batches);
+        }
+        let file_len = self.reader.seek(SeekFrom::End(0))?;
+        let section_marker = match self.section_marker {
+            Some(marker) => marker,
+            None => {
+                let start = FILE_SIGNATURE.len() as u64;
+                let Some(bytes) =
+                    read_at(&mut self.reader, 0, start + SECTION_MARKER_LEN, file_len)?
+                else {
+                    return Ok(batches);
+                };
+                if bytes[..FILE_SIGNATURE.len()] != FILE_SIGNATURE {
+                    return Err(Pod5Error::SignatureFailure("Start"));
+                }
+                let marker = bytes[FILE_SIGNATURE.len()..].try_into().expect("16 bytes");
+                self.section_marker = Some(marker);
+                self.next_table = start + SECTION_MARKER_LEN;
+                marker
+            }
+        };
+
+        loop {
+            let Some(table) = self.table.as_mut() else {
+                let Some(magic) = read_at(&mut self.reader, self.next_table, 8, file_len)? else {
+                    break;
+                };
+                if magic == ARROW_MAGIC {
+                    self.table = Some(FollowedTable::new(self.next_table));
+                    continue;
+                }
+                if magic == FOOTER_MAGIC {
+                    self.finished = self.footer_written(file_len)?;
+                    break;
+                }
+                return Err(Pod5Error::InvalidTable(self.next_table));
+            };
+            let corrupt = self.corrupt_signal.as_ref();
+            match table.step(
+                &mut self.reader,
+                file_len,
+                &section_marker,
+                corrupt,
+                &mut batches,
+            )? {
+                Step::Read => (),
+                Step::Waiting => break,
+                Step::Ended(next_table) => {
+                    self.next_table = next_table;
+                    self.table = None;
+                }
+            }
+        }
+        Ok(batches)
+    }
+
+    /// Whether the rest of the file after the footer magic has been written.
+    fn footer_written(&mut self, file_len: u64) -> Result<bool, Pod5Error> {
+        let start = file_len.saturating_sub(FILE_SIGNATURE.len() as u64);
+        if start <= self.next_table {
+            return Ok(false);
+        }
+        let signature = read_at(&mut self.reader, start, 8, file_len)?;
+        if signature.as_deref() != Some(&FILE_SIGNATURE[..]) {
+            return Ok(false);
+        }
+        Ok(ParsedFooter::read_footer(&mut self.reader).is_ok())
+    }
+
+    /// Open the finished file with its footer.
+    ///
+    /// This can be called before the file is finished, but will fail unless
+    /// the footer has been written since the last call to [`Follow::poll`].
+    pub fn into_reader(mut self) -> Result<Reader<R>, Pod5Error> {
+        self.reader.rewind()?;
+        let mut reader = Reader::from_reader(self.reader)?;
+        reader.corrupt_signal = self.corrupt_signal;
+        Ok(reader)
+    }
+}
+
+/// What happened when reading the next message of a table.
+enum Step {
+    /// A message was read.
+    Read,
+    /// The next message hasn't been completely written yet.
+    Waiting,
+    /// The table has ended, with the next table or footer at this offset.
+    Ended(u64),
+}
+
+/// The table currently being followed.
+struct FollowedTable {
+    offset: u64,
+    /// Offset of the next message in the Arrow stream.
+    next_message: u64,
+    /// Complete messages that haven't been decoded yet.
+    buffer: MessageBuffer,
+    /// Created once the schema message has been read.
+    stream: Option<TableStream>,
+    /// Where to continue looking for the section marker after the table,
+    /// once the end of the Arrow stream has been read.
+    marker_search: Option<u64>,
+}
+
+struct TableStream {
+    content: Option<(ContentType, Option<String>)>,
+    fields: Vec<Field>,
+    reader: StreamReader<MessageBuffer>,
+    /// Number of rows read so far
+    rows: u64,
+    /// Batches of a table that couldn't be identified from its schema
+    /// message, kept until its Arrow footer has been written.
+    unidentified: Vec<DataFrame>,
+}
+
+impl FollowedTable {
+    fn new(offset: u64) -> Self {
+        Self {
+            offset,
+            next_message: offset + ARROW_MAGIC.len() as u64,
+            buffer: MessageBuffer::default(),
+            stream: None,
+            marker_search: None,
+        }
+    }
+
+    fn step<R: Read + Seek>(
+        &mut self,
+        reader: &mut R,
+        file_len: u64,
+        section_marker: &[u8],
+        corrupt: Option<&CorruptSignalLog>,
+        batches: &mut Vec<TableBatch>,
+    ) -> Result<Step, Pod5Error> {
+        if let Some(mut offset) = self.marker_search {
+            // The Arrow footer is written in one go after the stream, so
+            // look for the section marker instead of parsing it.
+            while let Some(bytes) = read_at(reader, offset, SECTION_MARKER_LEN, file_len)? {
+                if bytes == section_marker {
+                    self.identify(reader, offset, corrupt, batches)?;
+                    return Ok(Step::Ended(offset + SECTION_MARKER_LEN));
+                }
+                offset += 8;
+            }
+            self.marker_search = Some(offset);
+            return Ok(Step::Waiting);
+        }
+
+        let Some(prefix) = read_at(reader, self.next_message, 8, file_len)? else {
+            return Ok(Step::Waiting);
+        };
+        let (prefix_len, meta_len) = if prefix[..4] == CONTINUATION_MARKER {
+            (8, &prefix[4..])
+        } else {
+            // Messages written before the continuation marker was added
+            (4, &prefix[..4])
+        };
+        let meta_len = i32::from_le_bytes(meta_len.try_into().expect("4 bytes"));
+        let meta_len = u64::try_from(meta_len).map_err(|_| Pod5Error::InvalidTable(self.offset))?;
+        if meta_len == 0 {
+            self.marker_search = Some((self.next_message + prefix_len).next_multiple_of(8));
+            return Ok(Step::Read);
+        }
+
+        let Some(meta) = read_at(reader, self.next_message + prefix_len, meta_len, file_len)?
+        else {
+            return Ok(Step::Waiting);
+        };
+        let body_len = MessageRef::read_as_root(&meta)
+            .ok()
+            .and_then(|message| message.body_length().ok())
+            .and_then(|len| u64::try_from(len).ok())
+            .ok_or(Pod5Error::InvalidTable(self.offset))?;
+        let message_len = prefix_len + meta_len + body_len;
+        let Some(message) = read_at(reader, self.next_message, message_len, file_len)? else {
+            return Ok(Step::Waiting);
+        };
+        self.next_message += message_len;
+        self.buffer.extend(message);
+
+        let Some(stream) = self.stream.as_mut() else {
+            let metadata = read_stream_metadata(&mut self.buffer.clone())?;
+            self.stream = Some(TableStream {
+                content: classify_table(&metadata.schema, metadata.custom_schema_metadata.as_ref()),
+                fields: metadata.schema.iter_values().cloned().collect(),
+                reader: StreamReader::new(self.buffer.clone(), metadata, None),
+                rows: 0,
+                unidentified: Vec::new(),
+            });
+            return Ok(Step::Read);
+        };
+        match stream.reader.next() {
+            Some(Ok(StreamState::Some(batch))) => {
+                let df = batch_to_df(&stream.fields, batch)?;
+                let first_row = stream.rows;
+                stream.rows += df.height() as u64;
+                match stream.content.clone() {
+                    Some(content) => batches.extend(table_batch(content, df, first_row, corrupt)?),
+                    None => stream.unidentified.push(df),
+                }
+            }
+            // Dictionary batches are stored until the record batch using them
+            Some(Ok(StreamState::Waiting)) | None => (),
+            Some(Err(e)) => return Err(e.into()),
+        }
+        Ok(Step::Read)
+    }
+
+    /// Return the batches of a table that couldn't be identified by its
+    /// schema message, using the metadata in its Arrow footer, which ends
+    /// before the section marker at `end`.
+    fn identify<R: Read + Seek>(
+        &mut self,
+        reader: &mut R,
+        end: u64,
+        corrupt: Option<&CorruptSignalLog>,
+        batches: &mut Vec<TableBatch>,
+    ) -> Result<(), Pod5Error> {
+        let Some(stream) = self.stream.as_mut().filter(|s| !s.unidentified.is_empty()) else {
+            return Ok(());
+        };
+        let table = read_at(reader, self.offset, end - self.offset, end)?.unwrap_or_default();
+        let metadata = arrow_footer_metadata(&table);
+        if let Some(content) = classify_table(stream.reader.schema(), metadata.as_deref()) {
+            let mut first_row = 0;
+            for df in stream.unidentified.drain(..) {
+                let height = df.height() as u64;
+                batches.extend(table_batch(content.clone(), df, first_row, corrupt)?);
+                first_row += height;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn table_batch(
+    (content_type, name): (ContentType, Option<String>),
+    df: DataFrame,
+    first_row: u64,
+    corrupt: Option<&CorruptSignalLog>,
+) -> Result<Option<TableBatch>, Pod5Error> {
+    let batch = match (content_type, name) {
+        (ContentType::SignalTable, _) => TableBatch::Signal(
+            SignalDataFrame(df).decompress_signal_rows(|row| first_row + row as u64, corrupt)?,
+        ),
+        (ContentType::ReadsTable, _) => TableBatch::Read(ReadDataFrame(df)),
+        (ContentType::RunInfoTable, _) => TableBatch::RunInfo(RunInfoDataFrame(df)),
+        (ContentType::OtherIndex, Some(name)) => TableBatch::Other(OtherDataFrame::new(name, df)),
+        _ => return Ok(None),
+    };
+    Ok(Some(batch))
+}
+
+/// Complete Arrow messages waiting to be decoded, shared between the table
+/// and its stream reader.
+#[derive(Debug, Clone, Default)]
+struct MessageBuffer(Arc<Mutex<VecDeque<u8>>>);
+
+impl MessageBuffer {
+    fn extend(&self, bytes: Vec<u8>) {
+        self.0.lock().unwrap().extend(bytes);
+    }
+}
+
+impl Read for MessageBuffer {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().read(buf)
+    }
+}
+
+/// Read `len` bytes at `offset`, or `None` if they haven't been written yet.
+fn read_at<R: Read + Seek>(
+    reader: &mut R,
+    offset: u64,
+    len: u64,
+    file_len: u64,
+) -> io::Result<Option<Vec<u8>>> {
+    if offset + len > file_len {
+        return Ok(None);
+    }
+    let mut buf = vec![0u8; len as usize];
+    reader.seek(SeekFrom::Start(offset))?;
+    reader.read_exact(&mut buf)?;
+    Ok(Some(buf))
+}
+
+#[cfg(test)]
+mod test {
+    use std::{fs::File, io::Cursor};
+
+    use polars::prelude::{Column, DataFrame};
+
+    use super::*;
+    use crate::writer::Writer;
+
+    const PATH: &str = "../extra/multi_fast5_zip_v3.pod5";
+
+    fn heights(batches: &[TableBatch]) -> Vec<usize> {
+        batches
+            .iter()
+            .map(|batch| match batch {
+                TableBatch::Signal(df) => df.0.height(),
+                TableBatch::Read(df) => df.0.height(),
+                TableBatch::RunInfo(df) => df.0.height(),
+                TableBatch::Other(df) => df.df.height(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_follow_while_writing() -> eyre::Result<()> {
+        let mut reader = Reader::from_reader(File::open(PATH)?)?;
+        let signal = reader.signal_dfs()?.next().unwrap()?;
+        let reads = reader.read_dfs()?.next().unwrap()?;
+
+        let path = std::env::temp_dir().join(format!(
+            "pod5_polars_test_follow_{}.pod5",
+            uuid::Uuid::new_v4()
+        ));
+        let mut writer = Writer::from_writer(File::create(&path)?)?;
+        let mut follow = Reader::follow(File::open(&path)?);
+        assert!(follow.poll()?.is_empty());
+
+        let mut guard = writer.guard::<SignalDataFrame>();
+        guard.write_batch(&SignalDataFrame(signal.0.slice(0, 2)))?;
+        let batches = follow.poll()?;
+        assert_eq!(heights(&batches), [2]);
+        let TableBatch::Signal(df) = &batches[0] else {
+            panic!("expected Signal batch");
+        };
+        assert_eq!(df.0, signal.0.slice(0, 2));
+
+        guard.write_batch(&SignalDataFrame(signal.0.slice(2, 3)))?;
+        guard.write_batch(&SignalDataFrame(signal.0.slice(5, 1)))?;
+        assert_eq!(heights(&follow.poll()?), [3, 1]);
+        guard.finish()?;
+        assert!(follow.poll()?.is_empty());
+
+        writer.with_guard(|g| g.write_batch(&reads))?;
+        let labels = DataFrame::new(vec![Column::new("label".into(), [1u32, 2])])?;
+        writer.with_guard(|g| g.write_batch(&OtherDataFrame::new("labels", labels.clone())))?;
+        // User table names are only in the Arrow footer, so its batches are
+        // returned once the table is finished.
+        let batches = follow.poll()?;
+        assert_eq!(
+            batches,
+            [
+                TableBatch::Read(reads.clone()),
+                TableBatch::Other(OtherDataFrame::new("labels", labels))
+            ]
+        );
+        assert!(!follow.is_finished());
+
+        writer.finish()?;
+        assert!(follow.poll()?.is_empty());
+        assert!(follow.is_finished());
+        let mut reader = follow.into_reader()?;
+        assert_eq!(reader.read_dfs()?.next().unwrap()?, reads);
+        std::fs::remove_file(&path)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_follow_finished_file() -> eyre::Result<()> {
+        let mut follow = Follow::new(File::open(PATH)?);
+        let batches = follow.poll()?;
+        assert!(follow.is_finished());
+
+        let mut reader = Reader::from_reader(File::open(PATH)?)?;
+        let mut expected = Vec::new();
+        for df in reader.signal_dfs()? {
+            expected.push(TableBatch::Signal(df?));
+        }
+        for df in reader.run_info_dfs()? {
+            expected.push(TableBatch::RunInfo(df?));
+        }
+        for df in reader.read_dfs()? {
+            expected.push(TableBatch::Read(df?));
+        }
+        assert_eq!(batches.len(), expected.len());
+        for batch in expected {
+            assert!(batches.contains(&batch));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_follow_skip_corrupt_signal() -> eyre::Result<()> {
+        let mut data = std::fs::read(PATH)?;
+        let footer = ParsedFooter::from_bytes(&data)?.info()?;
+        let signal = footer.tables_of(ContentType::SignalTable).next().unwrap();
+        // Break the zstd frame of the first Signal table row
+        let start = signal.offset() as usize;
+        let table = &data[start..start + signal.length() as usize];
+        let frame = table
+            .windows(4)
+            .position(|w| w == [0x28, 0xb5, 0x2f, 0xfd])
+            .unwrap();
+        data[start + frame] = 0;
+
+        let mut follow = Reader::follow(Cursor::new(&data));
+        assert!(matches!(
+            follow.poll(),
+            Err(Pod5Error::SignalDecodeFailure { row: 0, .. })
+        ));
+
+        let mut follow = Reader::follow(Cursor::new(&data)).skip_corrupt_signal();
+        let batches = follow.poll()?;
+        let nulls = batches
+            .iter()
+            .map(|batch| match batch {
+                TableBatch::Signal(df) => df.0.column("signal").unwrap().null_count(),
+                _ => 0,
+            })
+            .sum::<usize>();
+        assert_eq!(nulls, 1);
+        assert_eq!(follow.corrupt_signal_report().rows().len(), 1);
+
+        let mut reader = follow.into_reader()?;
+        assert!(
+            reader
+                .get_read("0000173c-bf67-44e7-9a9c-1ad0bc728e74")?
+                .corrupt_signal()
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,514 @@
+//! Persistent read_id index across many POD5 files.
+//!
+//! Looking up a read in a directory of POD5 files normally means opening and
+//! scanning the Read table of every file. A [`ReadIndex`] records, for every
+//! read, which file it is in, which Signal table rows hold its signal and how
+//! many samples it has. The index can be saved to a compact sidecar file and
+//! loaded later, so that lookups only need to open the one file containing the
+//! read and read only the Signal table rows belonging to it.
+//!
+//! Each indexed file's `file_identifier` from the POD5 footer is stored with
+//! the index, so files that have been rewritten since the index was built are
+//! detected instead of returning the wrong signal.
+//!
+//! Within a single file, a [`ReadIdIndex`] can also be embedded as the POD5
+//! `ReadIdIndex` table, see [`Writer::with_read_id_index`]. Readers then find
+//! a read's Read table row with a binary search instead of a scan.
+//!
+//! [`Writer::with_read_id_index`]: crate::writer::Writer::with_read_id_index
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::{self, BufReader, BufWriter, Read, Write},
+    path::{Path, PathBuf},
+};
+
+use polars::{
+    error::PolarsError,
+    frame::DataFrame,
+    prelude::{Column, NamedFrom},
+    series::Series,
+};
+use uuid::Uuid;
+
+use crate::{
+    dataframe::ReadIdIndexDataFrame,
+    dataset::Dataset,
+    error::Pod5Error,
+    reader::{Reader, concat_signal},
+};
+
+const INDEX_MAGIC: [u8; 8] = *b"P5RIDX\0\x01";
+
+/// A POD5 file covered by a [`ReadIndex`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IndexedFile {
+    path: PathBuf,
+    file_identifier: String,
+}
+
+impl IndexedFile {
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// `file_identifier` from the footer when the file was indexed.
+    pub fn file_identifier(&self) -> &str {
+        &self.file_identifier
+    }
+
+    /// Check whether the file on disk still has the same `file_identifier`.
+    pub fn is_stale(&self) -> bool {
+        File::open(&self.path)
+            .map_err(Pod5Error::from)
+            .and_then(Reader::from_reader)
+            .and_then(|reader| file_identifier(&reader))
+            .map_or(true, |id| id != self.file_identifier)
+    }
+}
+
+/// Location of a single read.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IndexEntry {
+    file: u32,
+    signal_rows: Vec<u64>,
+    num_samples: u64,
+}
+
+impl IndexEntry {
+    /// Signal table rows containing the read's signal, in order.
+    pub fn signal_rows(&self) -> &[u64] {
+        &self.signal_rows
+    }
+
+    pub fn num_samples(&self) -> u64 {
+        self.num_samples
+    }
+}
+
+/// Index from read_id to the file and Signal table rows containing the read.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ReadIndex {
+    files: Vec<IndexedFile>,
+    entries: BTreeMap<Uuid, IndexEntry>,
+}
+
+impl ReadIndex {
+    /// Build an index by scanning the Read table of every file in the
+    /// dataset.
+    pub fn build(dataset: &Dataset) -> Result<Self, Pod5Error> {
+        let mut index = Self::default();
+        for path in dataset.paths() {
+            index.add_file(path)?;
+        }
+        Ok(index)
+    }
+
+    /// Scan a file's Read table and add every read to the index.
+    pub fn add_file<P: AsRef<Path>>(&mut self, path: P) -> Result<(), Pod5Error> {
+        let path = path.as_ref();
+        let mut reader = Reader::from_reader(File::open(path)?)?;
+        let file_identifier = file_identifier(&reader)?;
+        let file = self.files.len() as u32;
+
+        // Entries are only added once the whole file has been read, so a
+        // failure part way through leaves the index unchanged
+        let mut entries = Vec::new();
+        let columns = ["read_id".into(), "signal".into(), "num_samples".into()];
+        for read_df in reader.read_dfs_with_columns(&columns)? {
+            let read_df = read_df?.0;
+            let read_ids = read_df.column("read_id")?.str()?;
+            let signal = read_df.column("signal")?.list()?;
+            let num_samples = read_df.column("num_samples")?.u64()?;
+            for ((read_id, rows), num_samples) in read_ids.into_iter().zip(signal).zip(num_samples)
+            {
+                let (Some(read_id), Some(rows)) = (read_id, rows) else {
+                    continue;
+                };
+                let read_id = Uuid::parse_str(read_id)
+                    .map_err(|_| Pod5Error::InvalidIndex("invalid read_id in Read table"))?;
+                let entry = IndexEntry {
+                    file,
+                    signal_rows: rows.u64()?.into_no_null_iter().collect(),
+                    num_samples: num_samples.unwrap_or_default(),
+                };
+                entries.push((read_id, entry));
+            }
+        }
+        self.files.push(IndexedFile {
+            path: path.to_path_buf(),
+            file_identifier,
+        });
+        self.entries.extend(entries);
+        Ok(())
+    }
+
+    pub fn files(&self) -> &[IndexedFile] {
+        &self.files
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Find the file and Signal table rows for a read.
+    pub fn get(&self, read_id: &str) -> Option<(&IndexedFile, &IndexEntry)> {
+        let read_id = Uuid::parse_str(read_id).ok()?;
+        let entry = self.entries.get(&read_id)?;
+        Some((&self.files[entry.file as usize], entry))
+    }
+
+    /// Files that have changed or disappeared since they were indexed.
+    pub fn stale_files(&self) -> Vec<&IndexedFile> {
+        self.files.iter().filter(|f| f.is_stale()).collect()
+    }
+
+    /// Re-index any stale files, dropping files that no longer exist.
+    pub fn refresh(&mut self) -> Result<(), Pod5Error> {
+        if self.files.iter().all(|f| !f.is_stale()) {
+            return Ok(());
+        }
+        let paths = self
+            .files
+            .iter()
+            .map(|f| f.path.clone())
+            .filter(|path| path.exists())
+            .collect::<Vec<_>>();
+        *self = Self::build(&Dataset::new(paths))?;
+        Ok(())
+    }
+
+    /// Read the full signal of a read, opening only the file containing it
+    /// and reading only its Signal table rows.
+    ///
+    /// Returns [`Pod5Error::StaleIndex`] if the file has been rewritten since
+    /// it was indexed.
+    pub fn fetch_signal(&self, read_id: &str) -> Result<Vec<i16>, Pod5Error> {
+        let (file, entry) = self
+            .get(read_id)
+            .ok_or_else(|| Pod5Error::ReadNotFound(read_id.to_string()))?;
+        let mut reader = Reader::from_reader(File::open(&file.path)?)?;
+        if file_identifier(&reader)? != file.file_identifier {
+            return Err(Pod5Error::StaleIndex(file.path.clone()));
+        }
+        let signal_df = reader
+            .signal_table_reader()?
+            .take_rows(&entry.signal_rows)?;
+        // Corrupt signal is an error here rather than skipped
+        Ok(concat_signal(signal_df)?.unwrap_or_default())
+    }
+
+    /// Write the index in its binary sidecar format.
+    pub fn write<W: Write>(&self, mut writer: W) -> Result<(), Pod5Error> {
+        writer.write_all(&INDEX_MAGIC)?;
+        writer.write_all(&(self.files.len() as u32).to_le_bytes())?;
+        for file in &self.files {
+            write_bytes(&mut writer, file.path.to_string_lossy().as_bytes())?;
+            write_bytes(&mut writer, file.file_identifier.as_bytes())?;
+        }
+        writer.write_all(&(self.entries.len() as u64).to_le_bytes())?;
+        for (read_id, entry) in &self.entries {
+            writer.write_all(read_id.as_bytes())?;
+            writer.write_all(&entry.file.to_le_bytes())?;
+            writer.write_all(&entry.num_samples.to_le_bytes())?;
+            writer.write_all(&(entry.signal_rows.len() as u32).to_le_bytes())?;
+            for row in &entry.signal_rows {
+                writer.write_all(&row.to_le_bytes())?;
+            }
+        }
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Read an index written by [`ReadIndex::write`].
+    pub fn read<R: Read>(mut reader: R) -> Result<Self, Pod5Error> {
+        let mut magic = [0u8; 8];
+        reader.read_exact(&mut magic)?;
+        if magic != INDEX_MAGIC {
+            return Err(Pod5Error::InvalidIndex("not a POD5 read_id index"));
+        }
+        // Lengths aren't trusted for allocating, since a malformed index
+        // could claim anything
+        let num_files = read_u32(&mut reader)?;
+        let mut files = Vec::new();
+        for _ in 0..num_files {
+            let path = PathBuf::from(read_string(&mut reader)?);
+            let file_identifier = read_string(&mut reader)?;
+            files.push(IndexedFile {
+                path,
+                file_identifier,
+            });
+        }
+        let num_entries = read_u64(&mut reader)?;
+        let mut entries = BTreeMap::new();
+        for _ in 0..num_entries {
+            let mut read_id = [0u8; 16];
+            reader.read_exact(&mut read_id)?;
+            let file = read_u32(&mut reader)?;
+            if file >= num_files {
+                return Err(Pod5Error::InvalidIndex("entry refers to unknown file"));
+            }
+            let num_samples = read_u64(&mut reader)?;
+            let num_rows = read_u32(&mut reader)?;
+            let mut signal_rows = Vec::new();
+            for _ in 0..num_rows {
+                signal_rows.push(read_u64(&mut reader)?);
+            }
+            entries.insert(
+                Uuid::from_bytes(read_id),
+                IndexEntry {
+                    file,
+                    signal_rows,
+                    num_samples,
+                },
+            );
+        }
+        Ok(Self { files, entries })
+    }
+
+    /// Save the index to a sidecar file.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), Pod5Error> {
+        self.write(BufWriter::new(File::create(path)?))
+    }
+
+    /// Load an index from a sidecar file.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, Pod5Error> {
+        Self::read(BufReader::new(File::open(path)?))
+    }
+}
+
+/// Sorted index from read_id to the row of the Read table containing it.
+///
+/// This is the in-memory form of the `ReadIdIndex` table embedded in a POD5
+/// file, see [`Reader::read_id_index`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ReadIdIndex {
+    /// Sorted by read_id
+    entries: Vec<(Uuid, u64)>,
+}
+
+impl ReadIdIndex {
+    /// Build an index from the read_ids of a Read table, in table order.
+    pub fn from_read_ids<I: IntoIterator<Item = Uuid>>(read_ids: I) -> Self {
+        let mut entries = read_ids.into_iter().zip(0u64..).collect::<Vec<_>>();
+        entries.sort_unstable();
+        Self { entries }
+    }
+
+    /// Build an index from the record batches of an embedded index table.
+    pub(crate) fn from_dfs<I>(dfs: I) -> Result<Self, Pod5Error>
+    where
+        I: IntoIterator<Item = Result<DataFrame, Pod5Error>>,
+    {
+        let mut entries = Vec::new();
+        for df in dfs {
+            let df = df?;
+            let read_ids = df.column("read_id")?.str()?;
+            let rows = df.column("read_table_row")?.u64()?;
+            for (read_id, row) in read_ids.into_iter().zip(rows) {
+                let (Some(read_id), Some(row)) = (read_id, row) else {
+                    return Err(Pod5Error::InvalidIndex("null entry in embedded index"));
+                };
+                let read_id = Uuid::parse_str(read_id)
+                    .map_err(|_| Pod5Error::InvalidIndex("invalid read_id in embedded index"))?;
+                entries.push((read_id, row));
+            }
+        }
+        // Written sorted, but don't rely on other writers doing the same
+        if !entries.is_sorted() {
+            entries.sort_unstable();
+        }
+        Ok(Self { entries })
+    }
+
+    /// Read table row containing the read.
+    pub fn row(&self, read_id: &str) -> Option<u64> {
+        let read_id = Uuid::parse_str(read_id).ok()?;
+        self.entries
+            .binary_search_by_key(&read_id, |&(id, _)| id)
+            .ok()
+            .map(|idx| self.entries[idx].1)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Convert into a DataFrame for writing as the embedded index table.
+    pub fn to_dataframe(&self) -> Result<ReadIdIndexDataFrame, PolarsError> {
+        let read_ids = self
+            .entries
+            .iter()
+            .map(|(id, _)| id.to_string())
+            .collect::<Vec<_>>();
+        let rows = self.entries.iter().map(|&(_, row)| row).collect::<Vec<_>>();
+        let df = DataFrame::new(vec![
+            Column::from(Series::new("read_id".into(), read_ids)),
+            Column::from(Series::new("read_table_row".into(), rows)),
+        ])?;
+        Ok(ReadIdIndexDataFrame(df))
+    }
+}
+
+fn file_identifier<R>(reader: &Reader<R>) -> Result<String, Pod5Error> {
+    let info = reader.footer.info()?;
+    Ok(info.file_identifier().unwrap_or_default().to_string())
+}
+
+fn write_bytes<W: Write>(writer: &mut W, bytes: &[u8]) -> io::Result<()> {
+    writer.write_all(&(bytes.len() as u32).to_le_bytes())?;
+    writer.write_all(bytes)
+}
+
+fn read_u32<R: Read>(reader: &mut R) -> io::Result<u32> {
+    let mut buf = [0u8; 4];
+    reader.read_exact(&mut buf)?;
+    Ok(u32::from_le_bytes(buf))
+}
+
+fn read_u64<R: Read>(reader: &mut R) -> io::Result<u64> {
+    let mut buf = [0u8; 8];
+    reader.read_exact(&mut buf)?;
+    Ok(u64::from_le_bytes(buf))
+}
+
+fn read_string<R: Read>(reader: &mut R) -> Result<String, Pod5Error> {
+    let len = read_u32(reader)?;
+    let mut buf = Vec::new();
+    reader.take(len as u64).read_to_end(&mut buf)?;
+    if buf.len() != len as usize {
+        return Err(Pod5Error::InvalidIndex("string longer than the index"));
+    }
+    String::from_utf8(buf).map_err(|_| Pod5Error::InvalidIndex("invalid UTF-8 string"))
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::{
+        dataframe::{ReadDataFrame, RunInfoDataFrame, SignalDataFrame},
+        writer::Writer,
+    };
+
+    const V3: &str = "../extra/multi_fast5_zip_v3.pod5";
+
+    #[test]
+    fn test_read_index() -> eyre::Result<()> {
+        let index = ReadIndex::build(&Dataset::new([V3]))?;
+        let mut reader = Reader::from_reader(File::open(V3)?)?;
+        let num_reads = reader
+            .read_dfs()?
+            .flatten()
+            .map(|df| df.0.height())
+            .sum::<usize>();
+        assert_eq!(index.len(), num_reads);
+        assert!(index.stale_files().is_empty());
+
+        let read = reader.reads()?.next().unwrap()?;
+        let (file, entry) = index.get(read.read_id()).unwrap();
+        assert_eq!(file.path(), Path::new(V3));
+        assert_eq!(entry.num_samples(), read.samples() as u64);
+        assert_eq!(index.fetch_signal(read.read_id())?, read.signal());
+        Ok(())
+    }
+
+    #[test]
+    fn test_embedded_read_id_index() -> eyre::Result<()> {
+        let mut reader = Reader::from_reader(File::open(V3)?)?;
+        assert!(reader.read_id_index()?.is_none());
+
+        let path = std::env::temp_dir().join(format!(
+            "pod5_test_embedded_read_id_index_{}.pod5",
+            Uuid::new_v4()
+        ));
+        let mut writer = Writer::from_writer(File::create(&path)?)?.with_read_id_index();
+        let run_info = reader.run_info_dfs()?.collect::<Result<Vec<_>, _>>()?;
+        writer.with_guard::<RunInfoDataFrame, _>(|g| {
+            run_info.iter().try_for_each(|df| g.write_batch(df))
+        })?;
+        let signal = reader.signal_dfs()?.collect::<Result<Vec<_>, _>>()?;
+        writer.with_guard::<SignalDataFrame, _>(|g| {
+            signal.iter().try_for_each(|df| g.write_batch(df))
+        })?;
+        let reads = reader.read_dfs()?.collect::<Result<Vec<_>, _>>()?;
+        writer.with_guard::<ReadDataFrame, _>(|g| {
+            reads.iter().try_for_each(|df| g.write_batch(df))
+        })?;
+        writer.finish()?;
+
+        let mut indexed = Reader::from_reader(File::open(&path)?)?;
+        let num_reads = reads.iter().map(|df| df.0.height()).sum::<usize>();
+        let index = indexed.read_id_index()?.expect("index was written");
+        assert_eq!(index.len(), num_reads);
+
+        let last = reads.last().unwrap().0.column("read_id")?.str()?;
+        let read_ids = [
+            "0000173c-bf67-44e7-9a9c-1ad0bc728e74",
+            last.get(last.len() - 1).unwrap(),
+        ];
+        assert_eq!(index.row(read_ids[1]), Some(num_reads as u64 - 1));
+        assert_eq!(indexed.get_reads(&read_ids)?, reader.get_reads(&read_ids)?);
+        assert!(matches!(
+            indexed.get_read("00000000-0000-0000-0000-000000000000"),
+            Err(Pod5Error::ReadNotFound(_))
+        ));
+        std::fs::remove_file(path)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_index_roundtrip() -> eyre::Result<()> {
+        let index = ReadIndex::build(&Dataset::new([V3]))?;
+        let mut buf = Vec::new();
+        index.write(&mut buf)?;
+        assert_eq!(ReadIndex::read(Cursor::new(&buf))?, index);
+        assert!(ReadIndex::read(Cursor::new(&buf[1..])).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_index_malformed_lengths() -> eyre::Result<()> {
+        // Lengths far past the end of the input fail without allocating them
+        let mut buf = INDEX_MAGIC.to_vec();
+        buf.extend(u32::MAX.to_le_bytes());
+        buf.extend(u32::MAX.to_le_bytes());
+        buf.extend(b"path");
+        assert!(matches!(
+            ReadIndex::read(Cursor::new(&buf)),
+            Err(Pod5Error::InvalidIndex(_))
+        ));
+
+        let mut buf = INDEX_MAGIC.to_vec();
+        buf.extend(0u32.to_le_bytes());
+        buf.extend(u64::MAX.to_le_bytes());
+        assert!(ReadIndex::read(Cursor::new(&buf)).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_index_stale() -> eyre::Result<()> {
+        let mut index = ReadIndex::build(&Dataset::new([V3]))?;
+        index.files[0].file_identifier = Uuid::nil().to_string();
+        assert_eq!(index.stale_files().len(), 1);
+        let read_id = index.entries.keys().next().unwrap().to_string();
+        assert!(matches!(
+            index.fetch_signal(&read_id),
+            Err(Pod5Error::StaleIndex(_))
+        ));
+
+        index.refresh()?;
+        assert!(index.stale_files().is_empty());
+        assert!(index.fetch_signal(&read_id).is_ok());
+        Ok(())
+    }
+}
@@ -3,8 +3,16 @@ pub use polars;
 pub use polars_arrow;
 
 pub mod dataframe;
+pub mod dataset;
 pub mod error;
+pub mod follow;
+pub mod index;
+pub mod migrate;
+pub mod read_writer;
 pub mod reader;
+pub mod record;
+pub mod recover;
+pub mod scan;
 pub mod writer;
 
 const FILE_SIGNATURE: [u8; 8] = [0x8b, b'P', b'O', b'D', b'\r', b'\n', 0x1a, b'\n'];
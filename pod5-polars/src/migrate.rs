@@ -0,0 +1,182 @@
+//! Upgrading POD5 files to the current version of the specification.
+//!
+//! [`Reader`] already converts the tables of files older than v3 into the
+//! current layout while reading them. [`migrate`] writes those converted
+//! tables to a new file, the same as the official `pod5 update` command.
+use std::{
+    fs::File,
+    io::{Read, Seek, Write},
+    path::Path,
+};
+
+use pod5_format::footer_generated::minknow::reads_format::ContentType;
+
+use crate::{
+    dataframe::{OtherDataFrame, ReadDataFrame, RunInfoDataFrame, SignalDataFrame},
+    error::Pod5Error,
+    reader::Reader,
+    writer::Writer,
+};
+
+/// Write every table of `reader` to `output` as a new POD5 file using the
+/// current `ReadSchema`, `RunInfoSchema` and `SignalSchema`.
+///
+/// Files older than v3 get a Run Info table built from the run infos stored
+/// in their Read table, and Read table columns added since the file was
+/// written are filled in with the defaults used by `pod5 update`. Named user
+/// tables are copied as is, and a read_id index is written if the original
+/// file had one.
+///
+/// ```
+/// # use std::{fs::File, io::Cursor};
+/// # use pod5_polars::{migrate::migrate, reader::Reader};
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let mut reader = Reader::from_reader(File::open("../extra/multi_fast5_zip_v0.pod5")?)?;
+/// let mut output = Cursor::new(Vec::new());
+/// migrate(&mut reader, &mut output)?;
+///
+/// let mut migrated = Reader::from_reader(Cursor::new(output.into_inner()))?;
+/// assert_eq!(migrated.run_info_dfs()?.count(), 1);
+/// # Ok(())
+/// # }
+/// ```
+pub fn migrate<R, W>(reader: &mut Reader<R>, output: W) -> Result<(), Pod5Error>
+where
+    R: Read + Seek,
+    W: Write + Seek,
+{
+    let mut writer = Writer::from_writer(output)?;
+    if reader.read_id_index()?.is_some() {
+        writer = writer.with_read_id_index();
+    }
+
+    let mut guard = writer.guard::<RunInfoDataFrame>();
+    for df in reader.run_info_dfs()? {
+        guard.write_batch(&df?)?;
+    }
+    guard.finish()?;
+
+    let mut guard = writer.guard::<ReadDataFrame>();
+    for df in reader.read_dfs()? {
+        guard.write_batch(&df?)?;
+    }
+    guard.finish()?;
+
+    let mut guard = writer.guard::<SignalDataFrame>();
+    for df in reader.signal_dfs()?.raw() {
+        guard.write_batch(&df?)?;
+    }
+    guard.finish()?;
+
+    for table in reader.tables()? {
+        if table.content_type() != ContentType::OtherIndex {
+            continue;
+        }
+        let dfs = reader.open_table(&table)?;
+        let Some(name) = dfs.name().map(String::from) else {
+            continue;
+        };
+        let mut guard = writer.guard::<OtherDataFrame>();
+        for df in dfs {
+            guard.write_batch(&OtherDataFrame::new(name.clone(), df?))?;
+        }
+        guard.finish()?;
+    }
+
+    writer.finish()?;
+    Ok(())
+}
+
+/// Upgrade the POD5 file at `input`, writing the new file to `output`. See
+/// [`migrate`].
+pub fn migrate_file<P, Q>(input: P, output: Q) -> Result<(), Pod5Error>
+where
+    P: AsRef<Path>,
+    Q: AsRef<Path>,
+{
+    let mut reader = Reader::open_mmap(input)?;
+    migrate(&mut reader, File::create(output)?)
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::Cursor;
+
+    use polars_arrow::datatypes::{ArrowDataType, ArrowSchema, ExtensionType};
+
+    use super::*;
+    use crate::dataframe::schema::{
+        TableSchema, reads_schema::ReadSchema, run_info_schema::RunInfoSchema,
+        signal_schema::SignalSchema,
+    };
+
+    /// Column names and types, ignoring extension type metadata which is
+    /// empty in the schemas but left out when writing.
+    fn columns(schema: &ArrowSchema) -> Vec<(String, ArrowDataType)> {
+        schema
+            .iter_values()
+            .map(|field| {
+                let dtype = match &field.dtype {
+                    ArrowDataType::Extension(ext) => {
+                        ArrowDataType::Extension(Box::new(ExtensionType {
+                            metadata: None,
+                            ..(**ext).clone()
+                        }))
+                    }
+                    dtype => dtype.clone(),
+                };
+                (field.name.to_string(), dtype)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_migrate() -> eyre::Result<()> {
+        for version in 0..4 {
+            let path = format!("../extra/multi_fast5_zip_v{version}.pod5");
+            let mut reader = Reader::from_reader(File::open(&path)?)?;
+            let mut output = Cursor::new(Vec::new());
+            migrate(&mut reader, &mut output)?;
+            let mut migrated = Reader::from_reader(Cursor::new(output.into_inner()))?;
+
+            let content_types = migrated
+                .tables()?
+                .iter()
+                .map(|t| t.content_type())
+                .collect::<Vec<_>>();
+            assert_eq!(
+                content_types,
+                [
+                    ContentType::RunInfoTable,
+                    ContentType::ReadsTable,
+                    ContentType::SignalTable
+                ],
+                "{path}"
+            );
+            assert_eq!(
+                columns(migrated.read_dfs()?.table_reader.schema()),
+                columns(&ReadSchema::as_schema()),
+                "{path}"
+            );
+            assert_eq!(
+                columns(migrated.run_info_dfs()?.table_reader.schema()),
+                columns(&RunInfoSchema::as_schema()),
+                "{path}"
+            );
+            assert_eq!(
+                columns(migrated.signal_dfs()?.table_reader.schema()),
+                columns(&SignalSchema::as_schema()),
+                "{path}"
+            );
+
+            let reads = reader.reads()?.collect::<Result<Vec<_>, _>>()?;
+            let migrated_reads = migrated.reads()?.collect::<Result<Vec<_>, _>>()?;
+            assert_eq!(reads.len(), migrated_reads.len(), "{path}");
+            for (read, got) in reads.iter().zip(&migrated_reads) {
+                assert_eq!(read.read_id(), got.read_id());
+                assert_eq!(read.signal(), got.signal());
+            }
+        }
+        Ok(())
+    }
+}
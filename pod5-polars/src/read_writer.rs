@@ -0,0 +1,476 @@
+//! Writing POD5 files one read at a time.
+//!
+//! [`ReadWriter`] takes care of everything needed to go from reads to the
+//! three POD5 tables: signal is split into chunks and VBZ compressed, Signal
+//! table rows are assigned to each read, and run infos are collected into the
+//! Run Info table.
+use std::{
+    collections::BTreeMap,
+    io::{Seek, Write},
+};
+
+use polars::{
+    frame::DataFrame,
+    prelude::{
+        Column, DataType, IntoSeries, NamedFrom, StringChunked, StructChunked, TimeUnit, TimeZone,
+    },
+    series::Series,
+};
+use uuid::Uuid;
+
+use crate::{
+    dataframe::{ReadDataFrame, RunInfoDataFrame, SignalDataFrame},
+    writer::{WriteError, Writer},
+};
+
+/// Number of samples per Signal table row, the same as the official writer.
+pub const DEFAULT_SIGNAL_CHUNK_SIZE: usize = 102_400;
+
+/// Metadata for a single read, written to the Read table.
+///
+/// `run_info` is the acquisition_id of a [`RunInfo`] registered with
+/// [`ReadWriter::add_run_info`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReadMetadata {
+    pub read_id: Uuid,
+    pub read_number: u32,
+    pub start: u64,
+    pub median_before: f32,
+    pub num_minknow_events: u64,
+    pub tracked_scaling_scale: f32,
+    pub tracked_scaling_shift: f32,
+    pub predicted_scaling_scale: f32,
+    pub predicted_scaling_shift: f32,
+    pub num_reads_since_mux_change: u32,
+    pub time_since_mux_change: f32,
+    pub channel: u16,
+    pub well: u8,
+    pub pore_type: String,
+    pub calibration_offset: f32,
+    pub calibration_scale: f32,
+    pub end_reason: String,
+    pub end_reason_forced: bool,
+    pub run_info: String,
+}
+
+impl ReadMetadata {
+    /// Metadata for `read_id` from the run `run_info`, with every other
+    /// column set to the defaults used by the official tools.
+    pub fn new<S: Into<String>>(read_id: Uuid, run_info: S) -> Self {
+        Self {
+            read_id,
+            read_number: 0,
+            start: 0,
+            median_before: f32::NAN,
+            num_minknow_events: 0,
+            tracked_scaling_scale: f32::NAN,
+            tracked_scaling_shift: f32::NAN,
+            predicted_scaling_scale: f32::NAN,
+            predicted_scaling_shift: f32::NAN,
+            num_reads_since_mux_change: 0,
+            time_since_mux_change: 0.0,
+            channel: 0,
+            well: 0,
+            pore_type: "not_set".to_string(),
+            calibration_offset: 0.0,
+            calibration_scale: 1.0,
+            end_reason: "unknown".to_string(),
+            end_reason_forced: false,
+            run_info: run_info.into(),
+        }
+    }
+}
+
+/// Information about the acquisition a read came from, written to the Run
+/// Info table.
+///
+/// Times are in milliseconds since the Unix epoch.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct RunInfo {
+    pub acquisition_id: String,
+    pub acquisition_start_time: i64,
+    pub adc_max: i16,
+    pub adc_min: i16,
+    pub context_tags: BTreeMap<String, String>,
+    pub experiment_name: String,
+    pub flow_cell_id: String,
+    pub flow_cell_product_code: String,
+    pub protocol_name: String,
+    pub protocol_run_id: String,
+    pub protocol_start_time: i64,
+    pub sample_id: String,
+    pub sample_rate: u16,
+    pub sequencing_kit: String,
+    pub sequencer_position: String,
+    pub sequencer_position_type: String,
+    pub software: String,
+    pub system_name: String,
+    pub system_type: String,
+    pub tracking_id: BTreeMap<String, String>,
+}
+
+/// Writes a POD5 file from individual reads.
+///
+/// Reads are kept in memory, with their signal compressed, until
+/// [`ReadWriter::finish`] writes the Run Info, Read and Signal tables.
+///
+/// ```
+/// # use std::io::Cursor;
+/// # use pod5_polars::{read_writer::{ReadMetadata, ReadWriter, RunInfo}, reader::Reader, writer::Writer};
+/// # use uuid::Uuid;
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let mut output = Cursor::new(Vec::new());
+/// let mut writer = ReadWriter::new(Writer::from_writer(&mut output)?);
+/// writer.add_run_info(RunInfo {
+///     acquisition_id: "run".to_string(),
+///     sample_rate: 4000,
+///     ..Default::default()
+/// });
+/// let read_id = Uuid::new_v4();
+/// writer.add_read(ReadMetadata::new(read_id, "run"), &[1, 2, 3])?;
+/// writer.finish()?;
+///
+/// let mut reader = Reader::from_reader(Cursor::new(output.into_inner()))?;
+/// let read = reader.reads()?.next().unwrap()?;
+/// assert_eq!(read.read_id(), read_id.to_string());
+/// assert_eq!(read.signal(), [1, 2, 3]);
+/// # Ok(())
+/// # }
+/// ```
+pub struct ReadWriter<W>
+where
+    W: Write + Seek,
+{
+    writer: Writer<W>,
+    chunk_size: usize,
+    run_infos: BTreeMap<String, RunInfo>,
+    reads: Vec<ReadMetadata>,
+    /// Signal table rows of each read, in the same order as `reads`
+    read_signal_rows: Vec<Vec<u64>>,
+    /// Signal table rows as (read_id, VBZ compressed signal, samples)
+    signal_rows: Vec<(Uuid, Vec<u8>, u32)>,
+}
+
+impl<W: Write + Seek> ReadWriter<W> {
+    pub fn new(writer: Writer<W>) -> Self {
+        Self {
+            writer,
+            chunk_size: DEFAULT_SIGNAL_CHUNK_SIZE,
+            run_infos: BTreeMap::new(),
+            reads: Vec::new(),
+            read_signal_rows: Vec::new(),
+            signal_rows: Vec::new(),
+        }
+    }
+
+    /// Split signal into Signal table rows of at most `samples` samples,
+    /// instead of [`DEFAULT_SIGNAL_CHUNK_SIZE`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `samples` is 0.
+    pub fn with_chunk_size(mut self, samples: usize) -> Self {
+        assert!(samples > 0, "chunk size must be at least one sample");
+        self.chunk_size = samples;
+        self
+    }
+
+    /// Register a run info for reads to refer to. A run info with the same
+    /// acquisition_id replaces the previous one.
+    pub fn add_run_info(&mut self, run_info: RunInfo) {
+        self.run_infos
+            .insert(run_info.acquisition_id.clone(), run_info);
+    }
+
+    /// Add a read with its signal as ADC values.
+    ///
+    /// Returns an error if the read's run info hasn't been registered.
+    pub fn add_read(&mut self, read: ReadMetadata, signal: &[i16]) -> Result<(), WriteError> {
+        if !self.run_infos.contains_key(&read.run_info) {
+            return Err(WriteError::UnknownRunInfo(read.run_info));
+        }
+        let mut rows = Vec::with_capacity(signal.len().div_ceil(self.chunk_size));
+        for chunk in signal.chunks(self.chunk_size) {
+            let compressed = svb16::encode(chunk).map_err(WriteError::FailedToCompressSignal)?;
+            rows.push(self.signal_rows.len() as u64);
+            self.signal_rows
+                .push((read.read_id, compressed, chunk.len() as u32));
+        }
+        self.reads.push(read);
+        self.read_signal_rows.push(rows);
+        Ok(())
+    }
+
+    /// Add a read with its signal in picoamps, converted to ADC values with
+    /// the read's calibration.
+    pub fn add_read_picoamps(
+        &mut self,
+        read: ReadMetadata,
+        signal: &[f32],
+    ) -> Result<(), WriteError> {
+        let signal = signal
+            .iter()
+            .map(|pa| {
+                (pa / read.calibration_scale - read.calibration_offset)
+                    .round()
+                    .clamp(i16::MIN as f32, i16::MAX as f32) as i16
+            })
+            .collect::<Vec<_>>();
+        self.add_read(read, &signal)
+    }
+
+    /// Write the Run Info, Read and Signal tables and finish the file.
+    pub fn finish(mut self) -> Result<(), WriteError> {
+        let run_info_df = self.run_info_df()?;
+        let read_df = self.read_df()?;
+        let signal_df = self.signal_df()?;
+        self.writer.with_guard(|g| g.write_batch(&run_info_df))?;
+        self.writer.with_guard(|g| g.write_batch(&read_df))?;
+        self.writer.with_guard(|g| g.write_batch(&signal_df))?;
+        self.writer.finish()
+    }
+
+    fn run_info_df(&self) -> Result<RunInfoDataFrame, WriteError> {
+        let run_infos = self.run_infos.values().collect::<Vec<_>>();
+        let strings = |name: &str, f: fn(&RunInfo) -> &str| {
+            Column::new(
+                name.into(),
+                run_infos.iter().map(|r| f(r)).collect::<Vec<_>>(),
+            )
+        };
+        let timestamps = |name: &str, f: fn(&RunInfo) -> i64| {
+            Column::new(
+                name.into(),
+                run_infos.iter().map(|r| f(r)).collect::<Vec<_>>(),
+            )
+            .cast(&DataType::Datetime(
+                TimeUnit::Milliseconds,
+                Some(TimeZone::UTC),
+            ))
+        };
+        let maps = |name: &str, f: fn(&RunInfo) -> &BTreeMap<String, String>| {
+            let entries = run_infos
+                .iter()
+                .map(|r| map_entries(f(r)))
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok::<_, WriteError>(Column::new(name.into(), entries))
+        };
+        let df = DataFrame::new(vec![
+            strings("acquisition_id", |r| &r.acquisition_id),
+            timestamps("acquisition_start_time", |r| r.acquisition_start_time)?,
+            Column::new(
+                "adc_max".into(),
+                run_infos.iter().map(|r| r.adc_max).collect::<Vec<_>>(),
+            ),
+            Column::new(
+                "adc_min".into(),
+                run_infos.iter().map(|r| r.adc_min).collect::<Vec<_>>(),
+            ),
+            maps("context_tags", |r| &r.context_tags)?,
+            strings("experiment_name", |r| &r.experiment_name),
+            strings("flow_cell_id", |r| &r.flow_cell_id),
+            strings("flow_cell_product_code", |r| &r.flow_cell_product_code),
+            strings("protocol_name", |r| &r.protocol_name),
+            strings("protocol_run_id", |r| &r.protocol_run_id),
+            timestamps("protocol_start_time", |r| r.protocol_start_time)?,
+            strings("sample_id", |r| &r.sample_id),
+            Column::new(
+                "sample_rate".into(),
+                run_infos.iter().map(|r| r.sample_rate).collect::<Vec<_>>(),
+            ),
+            strings("sequencing_kit", |r| &r.sequencing_kit),
+            strings("sequencer_position", |r| &r.sequencer_position),
+            strings("sequencer_position_type", |r| &r.sequencer_position_type),
+            strings("software", |r| &r.software),
+            strings("system_name", |r| &r.system_name),
+            strings("system_type", |r| &r.system_type),
+            maps("tracking_id", |r| &r.tracking_id)?,
+        ])?;
+        Ok(RunInfoDataFrame(df))
+    }
+
+    fn read_df(&self) -> Result<ReadDataFrame, WriteError> {
+        let reads = &self.reads;
+        macro_rules! column {
+            ($name:ident) => {
+                Column::new(
+                    stringify!($name).into(),
+                    reads.iter().map(|r| r.$name.clone()).collect::<Vec<_>>(),
+                )
+            };
+        }
+        let signal = self
+            .read_signal_rows
+            .iter()
+            .map(|rows| Series::new("".into(), rows))
+            .collect::<Vec<_>>();
+        let num_samples = self
+            .read_signal_rows
+            .iter()
+            .map(|rows| {
+                rows.iter()
+                    .map(|&row| self.signal_rows[row as usize].2 as u64)
+                    .sum::<u64>()
+            })
+            .collect::<Vec<_>>();
+        let df = DataFrame::new(vec![
+            Column::new(
+                "read_id".into(),
+                reads
+                    .iter()
+                    .map(|r| r.read_id.to_string())
+                    .collect::<Vec<_>>(),
+            ),
+            Column::new("signal".into(), signal),
+            column!(read_number),
+            column!(start),
+            column!(median_before),
+            column!(num_minknow_events),
+            column!(tracked_scaling_scale),
+            column!(tracked_scaling_shift),
+            column!(predicted_scaling_scale),
+            column!(predicted_scaling_shift),
+            column!(num_reads_since_mux_change),
+            column!(time_since_mux_change),
+            Column::new("num_samples".into(), num_samples),
+            column!(channel),
+            column!(well),
+            column!(pore_type),
+            column!(calibration_offset),
+            column!(calibration_scale),
+            column!(end_reason),
+            column!(end_reason_forced),
+            column!(run_info),
+        ])?;
+        Ok(ReadDataFrame(df))
+    }
+
+    fn signal_df(&self) -> Result<SignalDataFrame, WriteError> {
+        let rows = &self.signal_rows;
+        let df = DataFrame::new(vec![
+            Column::new(
+                "read_id".into(),
+                rows.iter()
+                    .map(|(read_id, ..)| read_id.to_string())
+                    .collect::<Vec<_>>(),
+            ),
+            Column::new(
+                "signal".into(),
+                rows.iter()
+                    .map(|(_, signal, _)| signal.as_slice())
+                    .collect::<Vec<_>>(),
+            ),
+            Column::new(
+                "samples".into(),
+                rows.iter()
+                    .map(|&(.., samples)| samples)
+                    .collect::<Vec<_>>(),
+            ),
+        ])?;
+        Ok(SignalDataFrame(df))
+    }
+}
+
+/// Map as a Series of key/value structs, one element of a map column.
+fn map_entries(map: &BTreeMap<String, String>) -> Result<Series, WriteError> {
+    let keys = map
+        .keys()
+        .map(String::as_str)
+        .collect::<StringChunked>()
+        .into_series();
+    let values = map
+        .values()
+        .map(String::as_str)
+        .collect::<StringChunked>()
+        .into_series();
+    let entries = StructChunked::from_series(
+        "".into(),
+        map.len(),
+        [
+            keys.with_name("key".into()),
+            values.with_name("value".into()),
+        ]
+        .iter(),
+    )?;
+    Ok(entries.into_series())
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::reader::Reader;
+
+    #[test]
+    fn test_read_writer() -> eyre::Result<()> {
+        let mut output = Cursor::new(Vec::new());
+        let mut writer = ReadWriter::new(Writer::from_writer(&mut output)?).with_chunk_size(4);
+        for (acquisition_id, flow_cell_id) in [("a", "FLO1"), ("b", "FLO2")] {
+            writer.add_run_info(RunInfo {
+                acquisition_id: acquisition_id.to_string(),
+                flow_cell_id: flow_cell_id.to_string(),
+                sample_rate: 5000,
+                context_tags: BTreeMap::from([("basecall".to_string(), "off".to_string())]),
+                ..Default::default()
+            });
+        }
+        let reads = [
+            (Uuid::new_v4(), "a", (0..10).collect::<Vec<i16>>()),
+            (Uuid::new_v4(), "b", vec![]),
+            (Uuid::new_v4(), "a", (-4..0).collect()),
+        ];
+        for (read_id, run_info, signal) in &reads {
+            writer.add_read(ReadMetadata::new(*read_id, *run_info), signal)?;
+        }
+        let mut read = ReadMetadata::new(Uuid::new_v4(), "a");
+        read.calibration_offset = 10.0;
+        read.calibration_scale = 0.5;
+        writer.add_read_picoamps(read, &[5.0, 10.0, 6.0])?;
+        assert!(matches!(
+            writer.add_read(ReadMetadata::new(Uuid::new_v4(), "c"), &[1]),
+            Err(WriteError::UnknownRunInfo(run_info)) if run_info == "c"
+        ));
+        writer.finish()?;
+
+        let mut reader = Reader::from_reader(Cursor::new(output.into_inner()))?;
+        let read_back = reader.reads()?.collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(read_back.len(), 4);
+        for ((read_id, _, signal), read) in reads.iter().zip(&read_back) {
+            assert_eq!(read.read_id(), read_id.to_string());
+            assert_eq!(read.signal(), signal);
+        }
+        assert_eq!(read_back[3].signal(), [0, 10, 2]);
+
+        let read_df = reader.read_dfs()?.next().unwrap()?;
+        let rows = read_df
+            .0
+            .column("signal")?
+            .list()?
+            .into_iter()
+            .map(|rows| rows.unwrap().u64().unwrap().into_no_null_iter().collect())
+            .collect::<Vec<Vec<u64>>>();
+        assert_eq!(rows, [vec![0, 1, 2], vec![], vec![3], vec![4]]);
+        let num_samples = read_df.0.column("num_samples")?.u64()?;
+        assert_eq!(
+            num_samples.into_no_null_iter().collect::<Vec<_>>(),
+            [10, 0, 4, 3]
+        );
+
+        let run_info_df = reader.run_info_dfs()?.next().unwrap()?;
+        let flow_cells = run_info_df.0.column("flow_cell_id")?.str()?;
+        assert_eq!(
+            flow_cells.into_no_null_iter().collect::<Vec<_>>(),
+            ["FLO1", "FLO2"]
+        );
+        let tags = run_info_df
+            .0
+            .column("context_tags")?
+            .list()?
+            .get_as_series(1);
+        let tags = tags.unwrap();
+        let values = tags.struct_()?.field_by_name("value")?;
+        assert_eq!(values.str()?.get(0), Some("off"));
+        Ok(())
+    }
+}
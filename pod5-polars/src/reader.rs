@@ -1,16 +1,48 @@
 //! Reading from a POD5 file.
-use std::io::{Read, Seek, SeekFrom};
+use std::{
+    collections::{HashMap, HashSet},
+    fs::File,
+    io::{Cursor, Read, Seek, SeekFrom},
+    path::Path,
+    sync::{Arc, Mutex},
+};
 
-use pod5_format::{ParsedFooter, valid_signature};
+use memmap2::Mmap;
+use pod5_format::{
+    FILE_SIGNATURE, FooterError, FooterInfo, FormatError, ParsedFooter, TableInfo,
+    footer_generated::minknow::reads_format::ContentType, valid_signature,
+};
+use polars::{
+    frame::DataFrame,
+    prelude::{IdxCa, PlSmallStr},
+};
 
 use crate::{
-    dataframe::{ReadDataFrameIter, RunInfoDataFrameIter, SignalDataFrameIter},
+    dataframe::{
+        CorruptSignalLog, CorruptSignalReport, EmbeddedTableIter, ReadDataFrame, ReadDataFrameIter,
+        ReadTableReader, RunInfoDataFrameIter, SignalDataFrame, SignalDataFrameIter,
+        SignalTableReader, get_next_df, legacy::LegacyReads, read_to_dataframe,
+        read_to_dataframe_projected,
+    },
     error::Pod5Error,
+    follow::Follow,
+    index::ReadIdIndex,
+    record::{ReadRecord, ReadRecordIter},
 };
 
 pub struct Reader<R> {
-    pub(crate) reader: R,
+    /// Shared with the table iterators, which each read their own table from
+    /// it on demand.
+    pub(crate) reader: Arc<Mutex<R>>,
     pub(crate) footer: ParsedFooter,
+    /// Embedded read_id index, `None` until first used and `Some(None)` if
+    /// the file doesn't have one.
+    read_id_index: Option<Option<ReadIdIndex>>,
+    /// Converts the Read table of files older than v3, `None` until first
+    /// used and `Some(None)` for newer files.
+    legacy_reads: Option<Option<LegacyReads>>,
+    /// Set when skipping corrupt signal rows instead of returning an error.
+    pub(crate) corrupt_signal: Option<CorruptSignalLog>,
 }
 
 impl<R> Reader<R>
@@ -26,38 +58,423 @@ where
             return Err(Pod5Error::SignatureFailure("End"));
         }
         let footer = ParsedFooter::read_footer(&mut reader)?;
-        Ok(Self { reader, footer })
+        Ok(Self {
+            reader: Arc::new(Mutex::new(reader)),
+            footer,
+            read_id_index: None,
+            legacy_reads: None,
+            corrupt_signal: None,
+        })
+    }
+
+    /// Decode every signal row that can be decoded, giving rows with corrupt
+    /// signal null signal instead of returning
+    /// [`Pod5Error::SignalDecodeFailure`].
+    ///
+    /// Applies to the signal iterators created after this call. The skipped
+    /// rows are listed by [`Reader::corrupt_signal_report`]. Reads returned by
+    /// [`Reader::reads`] and [`Reader::get_read`] with any corrupt rows have
+    /// no signal at all and are flagged by [`ReadRecord::corrupt_signal`].
+    pub fn skip_corrupt_signal(mut self) -> Self {
+        self.corrupt_signal.get_or_insert_default();
+        self
+    }
+
+    /// Every corrupt signal row skipped so far, grouped by read with
+    /// [`CorruptSignalReport::by_read`]. Always empty unless
+    /// [`Reader::skip_corrupt_signal`] was used.
+    pub fn corrupt_signal_report(&self) -> CorruptSignalReport {
+        self.corrupt_signal
+            .as_ref()
+            .map(CorruptSignalLog::report)
+            .unwrap_or_default()
     }
 
-    pub fn signal_dfs(&mut self) -> Result<SignalDataFrameIter, Pod5Error> {
+    /// Follow a file that's still being written, before it has a footer. See
+    /// [`Follow`].
+    pub fn follow(reader: R) -> Follow<R> {
+        Follow::new(reader)
+    }
+
+    pub fn signal_dfs(&mut self) -> Result<SignalDataFrameIter<R>, Pod5Error> {
         let table = self.footer.signal_table()?;
         let offset = table.as_ref().offset() as u64;
         let length = table.as_ref().length() as u64;
-        let iter = SignalDataFrameIter::new(offset, length, &mut self.reader)?;
+        let iter = SignalDataFrameIter::new(
+            offset,
+            length,
+            self.reader.clone(),
+            self.corrupt_signal.clone(),
+        )?;
         Ok(iter)
     }
 
-    pub fn read_dfs(&mut self) -> Result<ReadDataFrameIter, Pod5Error> {
+    pub fn read_dfs(&mut self) -> Result<ReadDataFrameIter<R>, Pod5Error> {
         let table = self.footer.read_table()?;
         let offset = table.as_ref().offset() as u64;
         let length = table.as_ref().length() as u64;
-        let iter = ReadDataFrameIter::new(offset, length, &mut self.reader)?;
+        let legacy = self.legacy_reads()?;
+        let iter = ReadDataFrameIter::new(offset, length, self.reader.clone(), legacy)?;
         Ok(iter)
     }
 
-    pub fn run_info_dfs(&mut self) -> Result<RunInfoDataFrameIter, Pod5Error> {
-        let table = self.footer.run_info_table()?;
+    /// Iterate over the Read table, only reading the given columns.
+    pub fn read_dfs_with_columns(
+        &mut self,
+        columns: &[PlSmallStr],
+    ) -> Result<ReadDataFrameIter<R>, Pod5Error> {
+        let table = self.footer.read_table()?;
         let offset = table.as_ref().offset() as u64;
         let length = table.as_ref().length() as u64;
-        let iter = RunInfoDataFrameIter::new(offset, length, &mut self.reader)?;
+        let legacy = self.legacy_reads()?;
+        ReadDataFrameIter::with_columns(offset, length, self.reader.clone(), columns, legacy)
+    }
+
+    /// Iterate over the Run Info table.
+    ///
+    /// Files older than v3 don't have a Run Info table, so the run infos are
+    /// read from the Read table instead.
+    pub fn run_info_dfs(&mut self) -> Result<RunInfoDataFrameIter<R>, Pod5Error> {
+        let table = match self.footer.run_info_table() {
+            Ok(table) => table,
+            Err(FormatError::FooterError(FooterError::RunInfoTableMissing))
+                if self.legacy_reads()?.is_some() =>
+            {
+                let table = self.footer.read_table()?;
+                let offset = table.as_ref().offset() as u64;
+                let length = table.as_ref().length() as u64;
+                return RunInfoDataFrameIter::legacy(offset, length, self.reader.clone());
+            }
+            Err(e) => return Err(e.into()),
+        };
+        let offset = table.as_ref().offset() as u64;
+        let length = table.as_ref().length() as u64;
+        let iter = RunInfoDataFrameIter::new(offset, length, self.reader.clone())?;
         Ok(iter)
     }
+
+    /// Identity, version and embedded file listing from the footer.
+    pub fn footer_info(&self) -> Result<FooterInfo, Pod5Error> {
+        Ok(self.footer.info()?)
+    }
+
+    /// Every table embedded in the file, in footer order.
+    pub fn tables(&self) -> Result<Vec<TableInfo>, Pod5Error> {
+        Ok(self.footer.tables()?)
+    }
+
+    /// Iterate over any embedded table, such as one returned by
+    /// [`Reader::tables`].
+    pub fn open_table(&mut self, table: &TableInfo) -> Result<EmbeddedTableIter<R>, Pod5Error> {
+        let offset = table.offset() as u64;
+        let length = table.length() as u64;
+        EmbeddedTableIter::new(offset, length, self.reader.clone())
+    }
+
+    /// Names of the user tables embedded as `OtherIndex`, in footer order.
+    /// Tables written without a name are skipped.
+    pub fn other_table_names(&mut self) -> Result<Vec<String>, Pod5Error> {
+        let mut names = Vec::new();
+        for table in self.tables()? {
+            if table.content_type() != ContentType::OtherIndex {
+                continue;
+            }
+            if let Some(name) = self.open_table(&table)?.name() {
+                names.push(name.to_string());
+            }
+        }
+        Ok(names)
+    }
+
+    /// Iterate over the user table written with the given name, see
+    /// [`OtherDataFrame`](crate::dataframe::OtherDataFrame).
+    pub fn other_dfs(&mut self, name: &str) -> Result<EmbeddedTableIter<R>, Pod5Error> {
+        for table in self.tables()? {
+            if table.content_type() != ContentType::OtherIndex {
+                continue;
+            }
+            let iter = self.open_table(&table)?;
+            if iter.name() == Some(name) {
+                return Ok(iter);
+            }
+        }
+        Err(Pod5Error::OtherTableMissing(name.to_string()))
+    }
+
+    /// Random access to individual rows of the Signal table.
+    pub fn signal_table_reader(&mut self) -> Result<SignalTableReader<R>, Pod5Error> {
+        let table = self.footer.signal_table()?;
+        let offset = table.as_ref().offset() as u64;
+        let length = table.as_ref().length() as u64;
+        SignalTableReader::new(
+            offset,
+            length,
+            self.reader.clone(),
+            self.corrupt_signal.clone(),
+        )
+    }
+
+    /// Random access to individual rows of the Read table.
+    pub fn read_table_reader(&mut self) -> Result<ReadTableReader<R>, Pod5Error> {
+        let table = self.footer.read_table()?;
+        let offset = table.as_ref().offset() as u64;
+        let length = table.as_ref().length() as u64;
+        let legacy = self.legacy_reads()?;
+        ReadTableReader::new(offset, length, self.reader.clone(), legacy)
+    }
+
+    /// Converter for the Read table if the file is older than v3, detected
+    /// from the Read table schema.
+    ///
+    /// Files written before the Read table had a `num_samples` column need
+    /// the sample counts of every Signal table row, which are read once and
+    /// kept.
+    fn legacy_reads(&mut self) -> Result<Option<LegacyReads>, Pod5Error> {
+        if self.legacy_reads.is_none() {
+            let table = self.footer.read_table()?;
+            let offset = table.as_ref().offset() as u64;
+            let length = table.as_ref().length() as u64;
+            let (fields, _) = read_to_dataframe(
+                offset,
+                length,
+                Pod5Error::ReadTableMissing,
+                self.reader.clone(),
+            )?;
+            let legacy = if LegacyReads::is_legacy(&fields) {
+                let samples = LegacyReads::needs_signal_samples(&fields)
+                    .then(|| self.signal_samples())
+                    .transpose()?;
+                Some(LegacyReads::new(fields, samples))
+            } else {
+                None
+            };
+            self.legacy_reads = Some(legacy);
+        }
+        Ok(self.legacy_reads.clone().flatten())
+    }
+
+    /// Number of samples in each row of the Signal table.
+    fn signal_samples(&mut self) -> Result<Vec<u32>, Pod5Error> {
+        let table = self.footer.signal_table()?;
+        let offset = table.as_ref().offset() as u64;
+        let length = table.as_ref().length() as u64;
+        let (fields, mut table_reader) = read_to_dataframe_projected(
+            offset,
+            length,
+            Pod5Error::SignalTableMissing,
+            self.reader.clone(),
+            Some(&["samples".into()]),
+        )?;
+        let mut samples = Vec::new();
+        while let Some(df) = get_next_df(&fields, &mut table_reader) {
+            samples.extend(df?.column("samples")?.u32()?.into_no_null_iter());
+        }
+        Ok(samples)
+    }
+
+    /// The embedded read_id index, or `None` if the file was written without
+    /// one.
+    ///
+    /// The index is read once and kept for later lookups.
+    pub fn read_id_index(&mut self) -> Result<Option<&ReadIdIndex>, Pod5Error> {
+        if self.read_id_index.is_none() {
+            let index = match self.footer.read_id_index_table() {
+                Ok(table) => {
+                    let offset = table.as_ref().offset() as u64;
+                    let length = table.as_ref().length() as u64;
+                    let (fields, mut table_reader) = read_to_dataframe(
+                        offset,
+                        length,
+                        Pod5Error::InvalidIndex("embedded index isn't a valid Arrow table"),
+                        self.reader.clone(),
+                    )?;
+                    let dfs = std::iter::from_fn(|| get_next_df(&fields, &mut table_reader));
+                    Some(ReadIdIndex::from_dfs(dfs)?)
+                }
+                Err(FormatError::FooterError(FooterError::ReadIdIndexMissing)) => None,
+                Err(e) => return Err(e.into()),
+            };
+            self.read_id_index = Some(index);
+        }
+        Ok(self.read_id_index.as_ref().and_then(Option::as_ref))
+    }
+
+    /// Iterate over every read in Read table order, with the signal from
+    /// each of the read's Signal table rows concatenated together.
+    pub fn reads(&mut self) -> Result<ReadRecordIter<R>, Pod5Error> {
+        let read_dfs = self.read_dfs()?;
+        let signal_table = self.signal_table_reader()?;
+        Ok(ReadRecordIter::new(read_dfs, signal_table))
+    }
+
+    /// Get the metadata and full signal for a single read.
+    ///
+    /// Only the Signal table record batches containing the read's signal are
+    /// read. Returns [`Pod5Error::ReadNotFound`] if the read isn't in the file.
+    pub fn get_read(&mut self, read_id: &str) -> Result<ReadRecord, Pod5Error> {
+        let mut reads = self.get_reads(&[read_id])?;
+        Ok(reads.remove(0))
+    }
+
+    /// Get the metadata and full signal for multiple reads, returned in the
+    /// same order as `read_ids`.
+    ///
+    /// If the file has an embedded read_id index, only the Read table record
+    /// batches containing the reads are read. Otherwise the Read table is
+    /// scanned once for all of the reads. Either way, Signal table record
+    /// batches shared between reads are only read once.
+    pub fn get_reads(&mut self, read_ids: &[&str]) -> Result<Vec<ReadRecord>, Pod5Error> {
+        let metadata = match self.read_id_index()? {
+            Some(index) => {
+                let rows = read_ids
+                    .iter()
+                    .filter_map(|read_id| index.row(read_id))
+                    .collect::<Vec<_>>();
+                self.take_reads(&rows)?
+            }
+            None => self.find_reads(read_ids)?,
+        };
+        let mut signal_table = self.signal_table_reader()?;
+        read_ids
+            .iter()
+            .map(|&read_id| {
+                let read_df = metadata
+                    .get(read_id)
+                    .cloned()
+                    .ok_or_else(|| Pod5Error::ReadNotFound(read_id.to_string()))?;
+                let rows = signal_rows(&read_df)?;
+                let signal = concat_signal(signal_table.take_rows(&rows)?)?;
+                Ok(ReadRecord::new(
+                    read_id.to_string(),
+                    ReadDataFrame(read_df),
+                    signal,
+                ))
+            })
+            .collect()
+    }
+
+    /// Fetch the given Read table rows, keyed by read_id.
+    fn take_reads(&mut self, rows: &[u64]) -> Result<HashMap<String, DataFrame>, Pod5Error> {
+        let read_df = self.read_table_reader()?.take_rows(rows)?.0;
+        let mut found = HashMap::with_capacity(read_df.height());
+        for row in 0..read_df.height() {
+            let row_df = read_df.slice(row as i64, 1);
+            if let Some(read_id) = row_df.column("read_id")?.str()?.get(0) {
+                found.insert(read_id.to_string(), row_df);
+            }
+        }
+        Ok(found)
+    }
+
+    /// Scan the Read table for the rows matching `read_ids`, stopping once all
+    /// of them have been found.
+    fn find_reads(&mut self, read_ids: &[&str]) -> Result<HashMap<String, DataFrame>, Pod5Error> {
+        let mut wanted: HashSet<&str> = read_ids.iter().copied().collect();
+        let mut found = HashMap::with_capacity(wanted.len());
+        for read_df in self.read_dfs()? {
+            if wanted.is_empty() {
+                break;
+            }
+            let read_df = read_df?.0;
+            let idxs = read_df
+                .column("read_id")?
+                .str()?
+                .into_iter()
+                .enumerate()
+                .filter_map(|(idx, rid)| rid.filter(|rid| wanted.contains(rid)).map(|_| idx))
+                .map(|idx| idx as polars::prelude::IdxSize)
+                .collect::<Vec<_>>();
+            if idxs.is_empty() {
+                continue;
+            }
+            let matched = read_df.take(&IdxCa::from_vec("idx".into(), idxs))?;
+            for row in 0..matched.height() {
+                let row_df = matched.slice(row as i64, 1);
+                let read_id = row_df
+                    .column("read_id")?
+                    .str()?
+                    .get(0)
+                    .expect("read_id matched above")
+                    .to_string();
+                wanted.remove(read_id.as_str());
+                found.insert(read_id, row_df);
+            }
+        }
+        Ok(found)
+    }
+}
+
+impl Reader<Cursor<Mmap>> {
+    /// Open a POD5 file by memory mapping it.
+    ///
+    /// The file is mapped once and tables are read from the mapping instead
+    /// of with file reads, letting the OS page cache handle repeated reads of
+    /// the same tables. This isn't zero-copy: the Arrow IPC reader still
+    /// copies each record batch out of the mapping into its own buffers.
+    /// Every table in the footer is checked to lie within the file.
+    ///
+    /// The file must not be modified or truncated while the `Reader` is in
+    /// use, otherwise reads may return corrupted data or the process may
+    /// crash.
+    pub fn open_mmap<P: AsRef<Path>>(path: P) -> Result<Self, Pod5Error> {
+        let file = File::open(path)?;
+        // SAFETY: Callers are warned above against modifying the file while
+        // it is mapped.
+        let mmap = unsafe { Mmap::map(&file)? };
+        if !mmap.starts_with(&FILE_SIGNATURE) {
+            return Err(Pod5Error::SignatureFailure("Start"));
+        }
+        if !mmap.ends_with(&FILE_SIGNATURE) {
+            return Err(Pod5Error::SignatureFailure("End"));
+        }
+        let footer = ParsedFooter::from_bytes(&mmap)?;
+        for table in footer.tables()? {
+            if table.slice(&mmap).is_none() {
+                return Err(Pod5Error::InvalidTable(table.offset() as u64));
+            }
+        }
+        Ok(Self {
+            reader: Arc::new(Mutex::new(Cursor::new(mmap))),
+            footer,
+            read_id_index: None,
+            legacy_reads: None,
+            corrupt_signal: None,
+        })
+    }
+}
+
+/// Signal table row indices from the `signal` column of a single row Read
+/// table DataFrame.
+pub(crate) fn signal_rows(read_df: &DataFrame) -> Result<Vec<u64>, Pod5Error> {
+    let rows = read_df
+        .column("signal")?
+        .list()?
+        .get_as_series(0)
+        .map(|rows| rows.u64().map(|ca| ca.into_no_null_iter().collect()))
+        .transpose()?
+        .unwrap_or_default();
+    Ok(rows)
+}
+
+/// Concatenate the decompressed signal of every row in the DataFrame, in row
+/// order, or `None` if any row has null signal because it was corrupt.
+pub(crate) fn concat_signal(signal_df: SignalDataFrame) -> Result<Option<Vec<i16>>, Pod5Error> {
+    let mut signal = Vec::new();
+    // Reads without any signal rows get an empty DataFrame without columns
+    if signal_df.0.height() == 0 {
+        return Ok(Some(signal));
+    }
+    for chunk in signal_df.0.column("signal")?.list()?.into_iter() {
+        let Some(chunk) = chunk else {
+            return Ok(None);
+        };
+        signal.extend(chunk.i16()?.into_no_null_iter());
+    }
+    Ok(Some(signal))
 }
 
 #[cfg(test)]
 mod test {
-    use std::fs::File;
-
     use polars::prelude::IntoLazy;
 
     use super::*;
@@ -81,4 +498,155 @@ mod test {
         println!("{x:?}");
         Ok(())
     }
+
+    #[test]
+    fn test_open_mmap() -> eyre::Result<()> {
+        let path = "../extra/multi_fast5_zip_v3.pod5";
+        let mut mmap_reader = Reader::open_mmap(path)?;
+        let mut reader = Reader::from_reader(File::open(path)?)?;
+
+        for (mapped, read) in mmap_reader.read_dfs()?.zip(reader.read_dfs()?) {
+            assert_eq!(mapped?, read?);
+        }
+        for (mapped, read) in mmap_reader.signal_dfs()?.zip(reader.signal_dfs()?) {
+            assert_eq!(mapped?, read?);
+        }
+
+        let read_id = "0000173c-bf67-44e7-9a9c-1ad0bc728e74";
+        assert_eq!(mmap_reader.get_read(read_id)?, reader.get_read(read_id)?);
+
+        // Cutting out the middle of the file leaves the footer pointing past
+        // its end
+        let mut data = std::fs::read(path)?;
+        data.drain(24..24 + data.len() / 2);
+        let cut = std::env::temp_dir().join(format!(
+            "pod5_polars_test_open_mmap_cut_{}.pod5",
+            uuid::Uuid::new_v4()
+        ));
+        std::fs::write(&cut, &data)?;
+        assert!(matches!(
+            Reader::open_mmap(&cut),
+            Err(Pod5Error::InvalidTable(_))
+        ));
+        std::fs::remove_file(&cut)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_read() -> eyre::Result<()> {
+        let file = File::open("../extra/multi_fast5_zip_v3.pod5")?;
+        let mut reader = Reader::from_reader(file)?;
+
+        // Split across two signal rows, 102400 + 21227 samples
+        let read_id = "0000173c-bf67-44e7-9a9c-1ad0bc728e74";
+        let read = reader.get_read(read_id)?;
+        assert_eq!(read.read_id(), read_id);
+        assert_eq!(read.metadata().0.height(), 1);
+        assert_eq!(read.samples(), 102400 + 21227);
+        let num_samples = read.metadata().0.column("num_samples")?.u64()?.get(0);
+        assert_eq!(num_samples, Some(read.samples() as u64));
+
+        let signal_df = reader.signal_dfs()?.next().unwrap()?;
+        let expected = concat_signal(SignalDataFrame(signal_df.0.head(Some(2))))?.unwrap();
+        assert_eq!(read.signal(), expected);
+        assert_eq!(read.clone().into_parts().2, Some(expected));
+
+        assert!(matches!(
+            reader.get_read("00000000-0000-0000-0000-000000000000"),
+            Err(Pod5Error::ReadNotFound(_))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_reads() -> eyre::Result<()> {
+        let file = File::open("../extra/multi_fast5_zip_v3.pod5")?;
+        let mut reader = Reader::from_reader(file)?;
+        let num_reads = reader
+            .read_dfs()?
+            .flatten()
+            .map(|df| df.0.height())
+            .sum::<usize>();
+
+        let mut count = 0;
+        for read in reader.reads()? {
+            let read = read?;
+            let num_samples = read.metadata().0.column("num_samples")?.u64()?.get(0);
+            assert_eq!(num_samples, Some(read.samples() as u64));
+            count += 1;
+        }
+        assert_eq!(count, num_reads);
+
+        let first = reader.reads()?.next().unwrap()?;
+        assert_eq!(first, reader.get_read(first.read_id())?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_reads_order() -> eyre::Result<()> {
+        let file = File::open("../extra/multi_fast5_zip_v3.pod5")?;
+        let mut reader = Reader::from_reader(file)?;
+        let read_ids = reader
+            .read_dfs()?
+            .next()
+            .unwrap()?
+            .0
+            .column("read_id")?
+            .str()?
+            .into_no_null_iter()
+            .take(5)
+            .map(String::from)
+            .rev()
+            .collect::<Vec<_>>();
+        let read_ids = read_ids.iter().map(String::as_str).collect::<Vec<_>>();
+        let reads = reader.get_reads(&read_ids)?;
+        let got = reads.iter().map(ReadRecord::read_id).collect::<Vec<_>>();
+        assert_eq!(got, read_ids);
+        Ok(())
+    }
+
+    #[test]
+    fn test_skip_corrupt_signal() -> eyre::Result<()> {
+        let mut data = std::fs::read("../extra/multi_fast5_zip_v3.pod5")?;
+        let footer = ParsedFooter::from_bytes(&data)?.info()?;
+        let signal = footer.tables_of(ContentType::SignalTable).next().unwrap();
+        // Break the zstd frame of the first Signal table row
+        let start = signal.offset() as usize;
+        let table = &data[start..start + signal.length() as usize];
+        let frame = table
+            .windows(4)
+            .position(|w| w == [0x28, 0xb5, 0x2f, 0xfd])
+            .unwrap();
+        data[start + frame] = 0;
+
+        // Split across two signal rows, 102400 + 21227 samples
+        let read_id = "0000173c-bf67-44e7-9a9c-1ad0bc728e74";
+        let mut reader = Reader::from_reader(Cursor::new(&data))?;
+        assert!(matches!(
+            reader.get_read(read_id),
+            Err(Pod5Error::SignalDecodeFailure { row: 0, .. })
+        ));
+        assert!(reader.corrupt_signal_report().is_empty());
+
+        let mut reader = Reader::from_reader(Cursor::new(&data))?.skip_corrupt_signal();
+        let signal_df = reader.signal_dfs()?.next().unwrap()?;
+        assert_eq!(signal_df.0.column("signal")?.null_count(), 1);
+        let read = reader.get_read(read_id)?;
+        assert!(read.corrupt_signal());
+        assert_eq!(read.samples(), 0);
+        assert_eq!(read.clone().into_parts().2, None);
+        let reads = reader.reads()?.collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(reads[0], read);
+        assert!(reads[1..].iter().all(|read| !read.corrupt_signal()));
+
+        let report = reader.corrupt_signal_report();
+        assert_eq!(report.rows().len(), 1);
+        assert_eq!(report.rows()[0].row(), 0);
+        assert_eq!(report.rows()[0].read_id(), Some(read_id));
+        assert_eq!(
+            report.by_read().keys().copied().collect::<Vec<_>>(),
+            [read_id]
+        );
+        Ok(())
+    }
 }
@@ -0,0 +1,170 @@
+//! Per-read view of a POD5 file.
+use std::io::{Read, Seek};
+
+use polars::frame::DataFrame;
+
+use crate::{
+    dataframe::{ReadDataFrame, ReadDataFrameIter, SignalTableReader},
+    error::Pod5Error,
+    reader::{concat_signal, signal_rows},
+};
+
+/// A single read from a POD5 file, with its metadata from the Read table and
+/// the full decompressed signal, stitched together from every Signal table
+/// row that belongs to the read.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReadRecord {
+    pub(crate) read_id: String,
+    pub(crate) metadata: ReadDataFrame,
+    pub(crate) signal: Vec<i16>,
+    pub(crate) corrupt_signal: bool,
+}
+
+impl ReadRecord {
+    /// Record with `None` signal if any of the read's Signal table rows was
+    /// corrupt.
+    pub(crate) fn new(read_id: String, metadata: ReadDataFrame, signal: Option<Vec<i16>>) -> Self {
+        Self {
+            read_id,
+            metadata,
+            corrupt_signal: signal.is_none(),
+            signal: signal.unwrap_or_default(),
+        }
+    }
+
+    /// The read_id as a UUID string.
+    pub fn read_id(&self) -> &str {
+        &self.read_id
+    }
+
+    /// Single row DataFrame containing the read's Read table columns.
+    pub fn metadata(&self) -> &ReadDataFrame {
+        &self.metadata
+    }
+
+    /// Signal as i16 ADC values.
+    pub fn signal(&self) -> &[i16] {
+        &self.signal
+    }
+
+    /// Whether any of the read's Signal table rows was corrupt and skipped
+    /// with [`Reader::skip_corrupt_signal`], in which case the read has no
+    /// signal rather than only the rows that could be decoded.
+    ///
+    /// [`Reader::skip_corrupt_signal`]: crate::reader::Reader::skip_corrupt_signal
+    pub fn corrupt_signal(&self) -> bool {
+        self.corrupt_signal
+    }
+
+    /// Number of signal samples in the read.
+    pub fn samples(&self) -> usize {
+        self.signal.len()
+    }
+
+    /// The signal, empty if [`ReadRecord::corrupt_signal`].
+    pub fn into_signal(self) -> Vec<i16> {
+        self.signal
+    }
+
+    /// The read_id, metadata and signal, with `None` signal if
+    /// [`ReadRecord::corrupt_signal`].
+    pub fn into_parts(self) -> (String, ReadDataFrame, Option<Vec<i16>>) {
+        let signal = (!self.corrupt_signal).then_some(self.signal);
+        (self.read_id, self.metadata, signal)
+    }
+}
+
+/// Iterator over every read in a POD5 file, in Read table order, yielding the
+/// read's metadata joined with its full signal.
+///
+/// Only one Read table record batch and a handful of Signal table record
+/// batches are held in memory at a time.
+pub struct ReadRecordIter<R: Read + Seek> {
+    read_dfs: ReadDataFrameIter<R>,
+    signal_table: SignalTableReader<R>,
+    current: Option<DataFrame>,
+    row: usize,
+    /// Read table row of the start of the current batch
+    first_row: usize,
+}
+
+impl<R: Read + Seek> ReadRecordIter<R> {
+    pub(crate) fn new(read_dfs: ReadDataFrameIter<R>, signal_table: SignalTableReader<R>) -> Self {
+        Self {
+            read_dfs,
+            signal_table,
+            current: None,
+            row: 0,
+            first_row: 0,
+        }
+    }
+
+    /// The next row of the Read table, along with its row index.
+    fn next_row(&mut self) -> Option<Result<(usize, DataFrame), Pod5Error>> {
+        loop {
+            if let Some(df) = self.current.as_ref()
+                && self.row < df.height()
+            {
+                let row_df = df.slice(self.row as i64, 1);
+                self.row += 1;
+                return Some(Ok((self.first_row + self.row - 1, row_df)));
+            }
+            match self.read_dfs.next()? {
+                Ok(df) => {
+                    self.first_row += self.current.as_ref().map_or(0, DataFrame::height);
+                    self.current = Some(df.0);
+                    self.row = 0;
+                }
+                Err(e) => return Some(Err(e)),
+            }
+        }
+    }
+
+    fn build_record(&mut self, row: usize, read_df: DataFrame) -> Result<ReadRecord, Pod5Error> {
+        let read_id = read_df
+            .column("read_id")?
+            .str()?
+            .get(0)
+            .map(String::from)
+            .ok_or_else(|| Pod5Error::InvalidReadId {
+                row,
+                reason: "read_id is null".to_string(),
+            })?;
+        let rows = signal_rows(&read_df)?;
+        let signal = concat_signal(self.signal_table.take_rows(&rows)?)?;
+        Ok(ReadRecord::new(read_id, ReadDataFrame(read_df), signal))
+    }
+}
+
+impl<R: Read + Seek> Iterator for ReadRecordIter<R> {
+    type Item = Result<ReadRecord, Pod5Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let read_df = self.next_row()?;
+        Some(read_df.and_then(|(row, df)| self.build_record(row, df)))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs::File;
+
+    use polars::prelude::{Column, DataType};
+
+    use super::*;
+    use crate::reader::Reader;
+
+    #[test]
+    fn test_null_read_id() -> eyre::Result<()> {
+        let mut reader = Reader::from_reader(File::open("../extra/multi_fast5_zip_v3.pod5")?)?;
+        let mut reads = reader.reads()?;
+        let mut read_df = reader.read_dfs()?.next().unwrap()?.0.slice(0, 1);
+        read_df.with_column(Column::full_null("read_id".into(), 1, &DataType::String))?;
+        let err = reads.build_record(4, read_df).unwrap_err();
+        assert!(
+            matches!(err, Pod5Error::InvalidReadId { row: 4, .. }),
+            "{err}"
+        );
+        Ok(())
+    }
+}
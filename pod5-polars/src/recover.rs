@@ -0,0 +1,422 @@
+//! Recovering truncated POD5 files.
+//!
+//! When the program writing a POD5 file crashes, or a transfer is cut short,
+//! the footer listing the embedded tables is missing and the file can't be
+//! opened with [`Reader`](crate::reader::Reader). [`recover`] instead scans
+//! the file for the section markers that separate the tables, salvages every
+//! complete record batch from each Arrow table it finds, and writes them to a
+//! new, valid POD5 file.
+use std::{
+    fs::File,
+    io::{Cursor, Seek, Write},
+    path::Path,
+};
+
+use memmap2::Mmap;
+use pod5_format::footer_generated::minknow::reads_format::ContentType;
+use polars::{
+    frame::DataFrame,
+    prelude::{BooleanChunked, NewChunkedArray},
+};
+use polars_arrow::{
+    datatypes::Field,
+    io::ipc::read::{StreamReader, StreamState, read_stream_metadata},
+};
+
+use crate::{
+    FILE_SIGNATURE,
+    dataframe::{
+        OtherDataFrame, ReadDataFrame, RunInfoDataFrame, SignalDataFrame, arrow_footer_metadata,
+        batch_to_df, classify_table,
+    },
+    error::Pod5Error,
+    writer::{IntoTable, Writer},
+};
+
+const SECTION_MARKER_LEN: usize = 16;
+/// Arrow IPC file magic, padded to 8 bytes
+const ARROW_MAGIC: [u8; 8] = *b"ARROW1\0\0";
+
+/// A table found while recovering a POD5 file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecoveredTable {
+    offset: u64,
+    content_type: ContentType,
+    name: Option<String>,
+    batches: usize,
+    rows: usize,
+    complete: bool,
+    written: bool,
+}
+
+impl RecoveredTable {
+    /// Offset of the table in the damaged file.
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// Table type, guessed from the table's columns.
+    pub fn content_type(&self) -> ContentType {
+        self.content_type
+    }
+
+    /// Name of a user table, see
+    /// [`OtherDataFrame`](crate::dataframe::OtherDataFrame).
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// Number of record batches salvaged.
+    pub fn batches(&self) -> usize {
+        self.batches
+    }
+
+    /// Number of rows salvaged.
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    /// Whether every record batch of the table was salvaged.
+    pub fn complete(&self) -> bool {
+        self.complete
+    }
+
+    /// Whether the table was written to the recovered file. Only the first
+    /// table of each type is written, and read_id index tables are skipped
+    /// since they may not match the salvaged Read table.
+    pub fn written(&self) -> bool {
+        self.written
+    }
+}
+
+/// Summary of what [`recover`] salvaged.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RecoveryReport {
+    tables: Vec<RecoveredTable>,
+    dropped_reads: usize,
+}
+
+impl RecoveryReport {
+    /// Every table found, in the order they appear in the damaged file.
+    pub fn tables(&self) -> &[RecoveredTable] {
+        &self.tables
+    }
+
+    /// Number of salvaged reads that weren't written because part of their
+    /// signal was lost.
+    pub fn dropped_reads(&self) -> usize {
+        self.dropped_reads
+    }
+
+    /// Number of reads written to the recovered file.
+    pub fn recovered_reads(&self) -> usize {
+        self.written_rows(ContentType::ReadsTable) - self.dropped_reads
+    }
+
+    fn written_rows(&self, content_type: ContentType) -> usize {
+        self.tables
+            .iter()
+            .filter(|t| t.written && t.content_type == content_type)
+            .map(|t| t.rows)
+            .sum()
+    }
+}
+
+/// A table salvaged from the damaged file.
+struct Salvaged {
+    table: RecoveredTable,
+    dfs: Vec<DataFrame>,
+}
+
+/// Recover the tables of a damaged POD5 file in `data`, writing a new POD5
+/// file to `output`.
+///
+/// Tables are found by scanning for the section marker following the file's
+/// starting signature, so the start of the file must be intact. Reads whose
+/// signal points past the salvaged Signal table rows are dropped.
+///
+/// ```
+/// # use std::io::Cursor;
+/// # use pod5_polars::{reader::Reader, recover::recover};
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let data = std::fs::read("../extra/multi_fast5_zip_v3.pod5")?;
+/// // Cut off the footer
+/// let truncated = &data[..data.len() - 1000];
+/// let mut output = Cursor::new(Vec::new());
+/// let report = recover(truncated, &mut output)?;
+/// let mut reader = Reader::from_reader(Cursor::new(output.into_inner()))?;
+/// assert_eq!(reader.reads()?.count(), report.recovered_reads());
+/// # Ok(())
+/// # }
+/// ```
+pub fn recover<W: Write + Seek>(data: &[u8], output: W) -> Result<RecoveryReport, Pod5Error> {
+    if !data.starts_with(&FILE_SIGNATURE) {
+        return Err(Pod5Error::SignatureFailure("Start"));
+    }
+    let table_start = FILE_SIGNATURE.len() + SECTION_MARKER_LEN;
+    let marker = data
+        .get(FILE_SIGNATURE.len()..table_start)
+        .ok_or(Pod5Error::SignatureFailure("Start"))?;
+
+    // Every section marker is 8-byte aligned, and each table starts right
+    // after one.
+    let mut starts = vec![table_start];
+    let mut ends = Vec::new();
+    for offset in (table_start..data.len()).step_by(8) {
+        if data.get(offset..offset + SECTION_MARKER_LEN) == Some(marker) {
+            ends.push(offset);
+            starts.push(offset + SECTION_MARKER_LEN);
+        }
+    }
+    ends.push(data.len());
+
+    let salvaged = starts
+        .into_iter()
+        .zip(ends)
+        .filter_map(|(start, end)| salvage_table(start as u64, &data[start..end]))
+        .collect::<Vec<_>>();
+    write_salvaged(salvaged, output)
+}
+
+/// Recover the damaged POD5 file at `input`, writing a new POD5 file to
+/// `output`. See [`recover`].
+pub fn recover_file<P, Q>(input: P, output: Q) -> Result<RecoveryReport, Pod5Error>
+where
+    P: AsRef<Path>,
+    Q: AsRef<Path>,
+{
+    let file = File::open(input)?;
+    // SAFETY: The damaged file is only read, and isn't expected to be
+    // modified during recovery.
+    let mmap = unsafe { Mmap::map(&file)? };
+    recover(&mmap, File::create(output)?)
+}
+
+/// Read every complete record batch from the Arrow IPC file in `segment`.
+///
+/// The file's footer may be missing, so the batches are read as a stream
+/// following the leading magic. Returns `None` if the segment isn't an Arrow
+/// table, such as the POD5 footer, or its schema can't be read.
+fn salvage_table(offset: u64, segment: &[u8]) -> Option<Salvaged> {
+    let mut stream = Cursor::new(segment.strip_prefix(&ARROW_MAGIC)?);
+    let metadata = read_stream_metadata(&mut stream).ok()?;
+    let (content_type, name) =
+        classify_table(&metadata.schema, metadata.custom_schema_metadata.as_ref()).or_else(
+            || classify_table(&metadata.schema, arrow_footer_metadata(segment).as_deref()),
+        )?;
+    let fields = metadata
+        .schema
+        .iter_values()
+        .cloned()
+        .collect::<Vec<Field>>();
+
+    let mut dfs = Vec::new();
+    let mut reader = StreamReader::new(stream, metadata, None);
+    // The stream only ends at the end-of-stream marker written after the
+    // last batch, a truncated batch is either an error or looks like a stream
+    // waiting for more data.
+    let complete = loop {
+        match reader.next() {
+            Some(Ok(StreamState::Some(batch))) => match batch_to_df(&fields, batch) {
+                Ok(df) => dfs.push(df),
+                Err(_) => break false,
+            },
+            Some(Ok(StreamState::Waiting) | Err(_)) => break false,
+            None => break true,
+        }
+    };
+    let table = RecoveredTable {
+        offset,
+        content_type,
+        name,
+        batches: dfs.len(),
+        rows: dfs.iter().map(DataFrame::height).sum(),
+        complete,
+        written: false,
+    };
+    Some(Salvaged { table, dfs })
+}
+
+fn write_salvaged<W: Write + Seek>(
+    mut salvaged: Vec<Salvaged>,
+    output: W,
+) -> Result<RecoveryReport, Pod5Error> {
+    let mut writer = Writer::from_writer(output)?;
+    let mut report = RecoveryReport::default();
+
+    // Salvaged signal is left VBZ compressed, so it is copied as is.
+    let signal_rows = match first_of(&mut salvaged, ContentType::SignalTable) {
+        Some(s) => {
+            write_dfs(&mut writer, &s.dfs, SignalDataFrame)?;
+            s.table.written = true;
+            s.table.rows as u64
+        }
+        None => 0,
+    };
+    if let Some(s) = first_of(&mut salvaged, ContentType::RunInfoTable) {
+        write_dfs(&mut writer, &s.dfs, RunInfoDataFrame)?;
+        s.table.written = true;
+    }
+    if let Some(s) = first_of(&mut salvaged, ContentType::ReadsTable) {
+        let mut dfs = Vec::with_capacity(s.dfs.len());
+        for df in &s.dfs {
+            let mask = has_signal(df, signal_rows)?;
+            report.dropped_reads += mask.into_iter().filter(|keep| *keep == Some(false)).count();
+            dfs.push(df.filter(&mask)?);
+        }
+        write_dfs(&mut writer, &dfs, ReadDataFrame)?;
+        s.table.written = true;
+    }
+    for s in salvaged
+        .iter_mut()
+        .filter(|s| s.table.content_type == ContentType::OtherIndex)
+    {
+        let name = s.table.name.clone().unwrap_or_default();
+        write_dfs(&mut writer, &s.dfs, |df| {
+            OtherDataFrame::new(name.clone(), df)
+        })?;
+        s.table.written = true;
+    }
+    writer.finish()?;
+
+    report.tables = salvaged.into_iter().map(|s| s.table).collect();
+    Ok(report)
+}
+
+fn first_of(salvaged: &mut [Salvaged], content_type: ContentType) -> Option<&mut Salvaged> {
+    salvaged
+        .iter_mut()
+        .find(|s| s.table.content_type == content_type && s.table.batches > 0)
+}
+
+fn write_dfs<W, T, F>(writer: &mut Writer<W>, dfs: &[DataFrame], wrap: F) -> Result<(), Pod5Error>
+where
+    W: Write + Seek,
+    T: IntoTable,
+    F: Fn(DataFrame) -> T,
+{
+    let mut guard = writer.guard::<T>();
+    for df in dfs.iter().filter(|df| df.height() > 0) {
+        guard.write_batch(&wrap(df.clone()))?;
+    }
+    guard.finish()?;
+    Ok(())
+}
+
+/// Mask of the reads whose Signal table rows were all salvaged.
+fn has_signal(read_df: &DataFrame, signal_rows: u64) -> Result<BooleanChunked, Pod5Error> {
+    let signal = read_df.column("signal")?.list()?;
+    let mut mask = Vec::with_capacity(signal.len());
+    for rows in signal.into_iter() {
+        let keep = match rows {
+            Some(rows) => rows
+                .u64()?
+                .into_iter()
+                .all(|row| row.is_some_and(|row| row < signal_rows)),
+            None => false,
+        };
+        mask.push(keep);
+    }
+    Ok(BooleanChunked::from_slice("keep".into(), &mask))
+}
+
+#[cfg(test)]
+mod test {
+    use pod5_format::ParsedFooter;
+    use polars_arrow::io::ipc::read::read_file_metadata;
+
+    use super::*;
+    use crate::reader::Reader;
+
+    const PATH: &str = "../extra/multi_fast5_zip_v3.pod5";
+
+    fn recover_to_reader(data: &[u8]) -> eyre::Result<(RecoveryReport, Reader<Cursor<Vec<u8>>>)> {
+        let mut output = Cursor::new(Vec::new());
+        let report = recover(data, &mut output)?;
+        assert!(pod5_format::validate(&mut output)?.is_valid());
+        Ok((report, Reader::from_reader(output)?))
+    }
+
+    #[test]
+    fn test_recover_missing_footer() -> eyre::Result<()> {
+        let data = std::fs::read(PATH)?;
+        let footer = ParsedFooter::from_bytes(&data)?.info()?;
+        let end = footer
+            .tables()
+            .iter()
+            .map(|t| (t.offset() + t.length()) as usize)
+            .max()
+            .unwrap();
+        let (report, mut recovered) = recover_to_reader(&data[..end + 4])?;
+        assert_eq!(report.tables().len(), footer.tables().len());
+        assert!(report.tables().iter().all(|t| t.complete() && t.written()));
+        assert_eq!(report.dropped_reads(), 0);
+
+        let mut original = Reader::from_reader(Cursor::new(data.clone()))?;
+        let expected = original.reads()?.collect::<Result<Vec<_>, _>>()?;
+        let reads = recovered.reads()?.collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(reads.len(), expected.len());
+        for (read, expected) in reads.iter().zip(&expected) {
+            assert_eq!(read.read_id(), expected.read_id());
+            assert_eq!(read.signal(), expected.signal());
+        }
+        Ok(())
+    }
+
+    /// Copy of the test file with the Read table before the Signal table,
+    /// and the Signal table split into one batch per row.
+    fn reads_before_signal() -> eyre::Result<Vec<u8>> {
+        let mut reader = Reader::from_reader(File::open(PATH)?)?;
+        let mut output = Cursor::new(Vec::new());
+        let mut writer = Writer::from_writer(&mut output)?;
+        let run_info = reader.run_info_dfs()?.next().unwrap()?;
+        writer.with_guard(|g| g.write_batch(&run_info))?;
+        let reads = reader.read_dfs()?.next().unwrap()?;
+        writer.with_guard(|g| g.write_batch(&reads))?;
+        let signal = reader.signal_dfs()?.next().unwrap()?.0;
+        writer.with_guard::<SignalDataFrame, _>(|g| {
+            for row in 0..signal.height() {
+                g.write_batch(&SignalDataFrame(signal.slice(row as i64, 1)))?;
+            }
+            Ok(())
+        })?;
+        writer.finish()?;
+        Ok(output.into_inner())
+    }
+
+    #[test]
+    fn test_recover_truncated_table() -> eyre::Result<()> {
+        let data = reads_before_signal()?;
+        let footer = ParsedFooter::from_bytes(&data)?.info()?;
+        let signal = footer.tables_of(ContentType::SignalTable).next().unwrap();
+        let metadata = read_file_metadata(&mut Cursor::new(signal.slice(&data).unwrap()))?;
+        let last = metadata.blocks.last().unwrap();
+
+        // Cut the file halfway through the last Signal table batch, losing
+        // the read using that row.
+        let cut =
+            signal.offset() + last.offset + last.meta_data_length as i64 + last.body_length / 2;
+        let (report, mut recovered) = recover_to_reader(&data[..cut as usize])?;
+        let signal_table = report
+            .tables()
+            .iter()
+            .find(|t| t.content_type() == ContentType::SignalTable)
+            .unwrap();
+        assert!(!signal_table.complete());
+        assert_eq!(signal_table.batches(), metadata.blocks.len() - 1);
+        assert_eq!(report.dropped_reads(), 1);
+
+        let mut original = Reader::from_reader(Cursor::new(data.clone()))?;
+        let reads = recovered.reads()?.collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(reads.len(), report.recovered_reads());
+        assert!(!reads.is_empty());
+        for read in reads {
+            let expected = original.get_read(read.read_id())?;
+            assert_eq!(read.signal(), expected.signal());
+        }
+
+        // Starting signature is required to find the section marker
+        assert!(recover(&data[8..], Cursor::new(Vec::new())).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,312 @@
+//! Lazily scanning POD5 files
+//!
+//! [`scan_pod5`] builds a `LazyFrame` with one row per read, containing the
+//! Read table columns and the read's full decompressed signal. Projections and
+//! filters are pushed down into the scan, so only the Read table columns that
+//! are used get decoded, and the Signal table is only read for reads that
+//! pass the filter.
+use std::{
+    any::Any,
+    fs::File,
+    io::{Read, Seek},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use polars::{
+    error::PolarsError,
+    frame::DataFrame,
+    prelude::{
+        AnonymousScan, AnonymousScanArgs, Column, DataType, Expr, IntoLazy, LazyFrame, NamedFrom,
+        PlSmallStr, ScanArgsAnonymous, Schema, SchemaRef,
+    },
+    series::Series,
+};
+
+use crate::{
+    dataframe::{
+        SignalTableReader,
+        schema::{TableSchema, reads_schema::ReadSchema},
+    },
+    error::Pod5Error,
+    reader::{Reader, concat_signal, signal_rows},
+};
+
+/// Lazily scan the reads of a POD5 file.
+///
+/// The `signal` column contains the read's i16 ADC signal, concatenated from
+/// every Signal table row that belongs to the read, instead of the Signal
+/// table row indices stored in the Read table.
+///
+/// ```
+/// # use pod5_polars::{polars::prelude::*, scan::scan_pod5};
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let df = scan_pod5("../extra/multi_fast5_zip_v3.pod5")?
+///     .filter(col("channel").eq(lit(109u16)))
+///     .select([col("read_id"), col("signal")])
+///     .collect()?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn scan_pod5<P: AsRef<Path>>(path: P) -> Result<LazyFrame, Pod5Error> {
+    let scan = Pod5Scan::new(path.as_ref())?;
+    let args = ScanArgsAnonymous {
+        schema: Some(scan.schema.clone()),
+        name: "POD5 SCAN",
+        ..Default::default()
+    };
+    Ok(LazyFrame::anonymous_scan(Arc::new(scan), args)?)
+}
+
+struct Pod5Scan {
+    path: PathBuf,
+    schema: SchemaRef,
+}
+
+impl Pod5Scan {
+    fn new(path: &Path) -> Result<Self, Pod5Error> {
+        let mut reader = Reader::from_reader(File::open(path)?)?;
+        let read_dfs = reader.read_dfs()?;
+        // Files older than v3 are converted to the current layout when read
+        let fields = match read_dfs.is_legacy() {
+            true => ReadSchema::as_schema().iter_values().cloned().collect(),
+            false => read_dfs.fields().to_vec(),
+        };
+        let schema = fields
+            .iter()
+            .map(|field| {
+                let dtype = match field.name.as_str() {
+                    "read_id" => DataType::String,
+                    "signal" => DataType::List(Box::new(DataType::Int16)),
+                    _ => DataType::from_arrow_field(field),
+                };
+                (field.name.clone(), dtype)
+            })
+            .collect::<Schema>();
+        Ok(Self {
+            path: path.to_path_buf(),
+            schema: Arc::new(schema),
+        })
+    }
+
+    fn scan_reads(&self, args: AnonymousScanArgs) -> Result<DataFrame, Pod5Error> {
+        let columns = args
+            .with_columns
+            .map(|columns| columns.to_vec())
+            .unwrap_or_else(|| self.schema.iter_names().cloned().collect());
+        let predicate_columns = args
+            .predicate
+            .as_ref()
+            .map(leaf_column_names)
+            .unwrap_or_default();
+        let filter_on_signal = predicate_columns.iter().any(|c| c == "signal");
+        let decode_signal = filter_on_signal || columns.iter().any(|c| c == "signal");
+
+        let mut needed = columns.clone();
+        needed.extend(predicate_columns);
+
+        let mut reader = Reader::from_reader(File::open(&self.path)?)?;
+        let mut signal_table = if decode_signal {
+            Some(reader.signal_table_reader()?)
+        } else {
+            None
+        };
+
+        let mut acc: Option<DataFrame> = None;
+        for read_df in reader.read_dfs_with_columns(&needed)? {
+            let mut df = read_df?.0;
+            if let Some(predicate) = args.predicate.clone().filter(|_| !filter_on_signal) {
+                df = df.lazy().filter(predicate).collect()?;
+            }
+            if let Some(signal_table) = signal_table.as_mut() {
+                df = with_read_signal(df, signal_table)?;
+            }
+            if let Some(predicate) = args.predicate.clone().filter(|_| filter_on_signal) {
+                df = df.lazy().filter(predicate).collect()?;
+            }
+            let df = df.select(columns.iter().cloned())?;
+            match acc.as_mut() {
+                Some(acc) => {
+                    acc.vstack_mut(&df)?;
+                }
+                None => acc = Some(df),
+            }
+            if let (Some(acc), Some(n_rows)) = (acc.as_ref(), args.n_rows)
+                && acc.height() >= n_rows
+            {
+                break;
+            }
+        }
+
+        let df = match acc {
+            Some(df) => df,
+            None => DataFrame::empty_with_schema(&self.schema.try_project(&columns)?),
+        };
+        Ok(match args.n_rows {
+            Some(n_rows) => df.head(Some(n_rows)),
+            None => df,
+        })
+    }
+}
+
+impl AnonymousScan for Pod5Scan {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn scan(&self, scan_opts: AnonymousScanArgs) -> polars::prelude::PolarsResult<DataFrame> {
+        self.scan_reads(scan_opts).map_err(|e| match e {
+            Pod5Error::PolarsError(e) => e,
+            e => PolarsError::ComputeError(e.to_string().into()),
+        })
+    }
+
+    fn schema(
+        &self,
+        _infer_schema_length: Option<usize>,
+    ) -> polars::prelude::PolarsResult<SchemaRef> {
+        Ok(self.schema.clone())
+    }
+
+    fn allows_predicate_pushdown(&self) -> bool {
+        true
+    }
+
+    fn allows_projection_pushdown(&self) -> bool {
+        true
+    }
+}
+
+/// Names of every column used in an expression.
+fn leaf_column_names(expr: &Expr) -> Vec<PlSmallStr> {
+    let mut names = Vec::new();
+    expr.into_iter().for_each(|e| {
+        if let Expr::Column(name) = e {
+            names.push(name.clone());
+        }
+    });
+    names
+}
+
+/// Replace the Signal table row indices in the `signal` column with the
+/// read's full decompressed signal, or null if any of its rows was corrupt.
+fn with_read_signal<R: Read + Seek>(
+    mut df: DataFrame,
+    signal_table: &mut SignalTableReader<R>,
+) -> Result<DataFrame, Pod5Error> {
+    let mut signals = Vec::with_capacity(df.height());
+    for row in 0..df.height() {
+        let rows = signal_rows(&df.slice(row as i64, 1))?;
+        let signal = concat_signal(signal_table.take_rows(&rows)?)?;
+        signals.push(signal.map(|signal| Series::new("".into(), signal)));
+    }
+    let signal =
+        Series::new("signal".into(), signals).cast(&DataType::List(Box::new(DataType::Int16)))?;
+    df.with_column(Column::from(signal))?;
+    Ok(df)
+}
+
+#[cfg(test)]
+mod test {
+    use polars::prelude::{col, lit};
+
+    use super::*;
+
+    const PATH: &str = "../extra/multi_fast5_zip_v3.pod5";
+
+    const PATHS: [&str; 4] = [
+        "../extra/multi_fast5_zip_v0.pod5",
+        "../extra/multi_fast5_zip_v1.pod5",
+        "../extra/multi_fast5_zip_v2.pod5",
+        "../extra/multi_fast5_zip_v3.pod5",
+    ];
+
+    #[test]
+    fn test_scan_pod5() -> eyre::Result<()> {
+        let df = scan_pod5(PATH)?.collect()?;
+        let mut reader = Reader::from_reader(File::open(PATH)?)?;
+        let num_reads = reader
+            .read_dfs()?
+            .flatten()
+            .map(|df| df.0.height())
+            .sum::<usize>();
+        assert_eq!(df.height(), num_reads);
+        assert_eq!(
+            df.schema().as_ref(),
+            scan_pod5(PATH)?.collect_schema()?.as_ref()
+        );
+
+        let first = reader.reads()?.next().unwrap()?;
+        let signal = df.column("signal")?.list()?.get_as_series(0).unwrap();
+        assert_eq!(
+            signal.i16()?.into_no_null_iter().collect::<Vec<_>>(),
+            first.signal()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_scan_pod5_pushdown() -> eyre::Result<()> {
+        let all = scan_pod5(PATH)?.collect()?;
+        let channel = all.column("channel")?.u16()?.get(0).unwrap();
+        let expected = all
+            .clone()
+            .lazy()
+            .filter(col("channel").eq(lit(channel)))
+            .select([col("read_id"), col("num_samples")])
+            .collect()?;
+
+        let df = scan_pod5(PATH)?
+            .filter(col("channel").eq(lit(channel)))
+            .select([col("read_id"), col("num_samples")])
+            .collect()?;
+        assert_eq!(df, expected);
+        assert!(df.height() > 0);
+
+        // Filter on the decompressed signal
+        let df = scan_pod5(PATH)?
+            .filter(col("signal").list().len().gt(lit(100_000u32)))
+            .select([col("read_id")])
+            .collect()?;
+        let expected = all
+            .lazy()
+            .filter(col("num_samples").gt(lit(100_000u64)))
+            .select([col("read_id")])
+            .collect()?;
+        assert_eq!(df, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn test_scan_pod5_versions() -> eyre::Result<()> {
+        let expected = scan_pod5(PATH)?.collect()?;
+        let channel = expected.column("channel")?.u16()?.get(0).unwrap();
+        let query = |lf: LazyFrame| {
+            lf.filter(col("channel").eq(lit(channel))).select([
+                col("read_id"),
+                col("channel"),
+                col("num_minknow_events"),
+            ])
+        };
+        let expected_query = query(expected.clone().lazy()).collect()?;
+        assert!(expected_query.height() > 0);
+
+        for path in PATHS {
+            let lf = scan_pod5(path)?;
+            assert_eq!(
+                lf.clone().collect_schema()?,
+                expected.schema().clone(),
+                "{path}"
+            );
+            let df = lf.clone().collect()?;
+            assert_eq!(df.schema(), expected.schema(), "{path}");
+            assert_eq!(
+                df.select(["read_id", "num_samples", "signal"])?,
+                expected.select(["read_id", "num_samples", "signal"])?,
+                "{path}"
+            );
+            assert_eq!(query(lf).collect()?, expected_query, "{path}");
+        }
+        Ok(())
+    }
+}
@@ -2,35 +2,49 @@
 //!
 //! Provides an interface for writing dataframes as POD5 tables.
 use std::{
-    collections::{BTreeMap, HashSet},
-    io::{Seek, Write},
+    borrow::Cow,
+    collections::HashSet,
+    fs::{self, File},
+    io::{Read, Seek, SeekFrom, Write},
     marker::PhantomData,
+    path::{Path, PathBuf},
     sync::Arc,
 };
 
-use pod5_format::{FOOTER_MAGIC, TableInfo, footer_generated::minknow::reads_format::ContentType};
+use pod5_format::{
+    FOOTER_MAGIC, FooterError, FormatError, ParsedFooter, TableInfo,
+    footer_generated::minknow::reads_format::ContentType,
+};
 use polars::{
     error::PolarsError,
     frame::DataFrame,
-    prelude::{CompatLevel, PlSmallStr},
+    prelude::{CategoricalOrdering, CompatLevel, DataType, Field, PlSmallStr, TimeUnit},
+};
+use polars_arrow::{
+    array::Array,
+    datatypes::{ArrowDataType, ArrowSchema, Metadata},
+    io::ipc::write::FileWriter,
+    record_batch::RecordBatchT,
 };
-use polars_arrow::{datatypes::Metadata, io::ipc::write::FileWriter};
 use uuid::Uuid;
 
 use crate::{
     FILE_SIGNATURE,
     dataframe::{
-        ReadDataFrame, RunInfoDataFrame, SignalDataFrame,
-        compatibility::record_batch_to_compat,
+        OtherDataFrame, ReadDataFrame, ReadIdIndexDataFrame, RunInfoDataFrame, SignalDataFrame,
+        TABLE_NAME_KEY,
+        compatibility::{CompatError, record_batch_to_compat},
+        legacy,
         schema::{
-            TableSchema, reads_schema::ReadSchema, run_info_schema::RunInfoSchema,
-            signal_schema::SignalSchema,
+            TableSchema, other_schema::OtherSchema, read_id_index_schema::ReadIdIndexSchema,
+            reads_schema::ReadSchema, run_info_schema::RunInfoSchema, signal_schema::SignalSchema,
         },
     },
+    index::ReadIdIndex,
+    reader::Reader,
 };
 
 const SOFTWARE: &str = "pod5-rs";
-const POD5_VERSION: &str = "0.0.40";
 
 #[derive(Debug, thiserror::Error)]
 pub enum WriteError {
@@ -60,6 +74,49 @@ pub enum WriteError {
 
     #[error("Writer: Failed to write footer length as bytes")]
     FailedToWriteFooterLengthBytes,
+
+    #[error("Writer: invalid read_id for read_id index: {0}")]
+    InvalidReadId(String),
+
+    #[error("Writer: failed to convert batch to POD5 types: {0}")]
+    CompatError(#[from] CompatError),
+
+    #[error("Writer: missing column {column}, expected type {expected}")]
+    MissingColumn {
+        column: String,
+        expected: Box<DataType>,
+    },
+
+    #[error("Writer: column {0} is not part of the table schema")]
+    UnexpectedColumn(String),
+
+    #[error("Writer: failed to create temporary file: {0}")]
+    FailedToCreateTempFile(std::io::Error),
+
+    #[error("Writer: failed to move finished file into place: {0}")]
+    FailedToPersist(std::io::Error),
+
+    #[error("Writer: failed to open POD5 file for appending: {0}")]
+    FailedToOpenForAppend(std::io::Error),
+
+    #[error("Writer: invalid footer in file opened for appending: {0}")]
+    InvalidFooter(#[from] FormatError),
+
+    #[error("Writer: read refers to a run info that wasn't added: {0}")]
+    UnknownRunInfo(String),
+
+    #[error("Writer: failed to compress signal: {0}")]
+    FailedToCompressSignal(std::io::Error),
+
+    #[error("Writer: more than 32768 distinct {0} values for a legacy Read table batch")]
+    TooManyDictionaryValues(String),
+
+    #[error("Writer: column {column} has type {actual}, expected {expected}")]
+    ColumnType {
+        column: String,
+        expected: Box<DataType>,
+        actual: Box<DataType>,
+    },
 }
 
 #[derive(Debug, Clone)]
@@ -67,6 +124,7 @@ pub enum TableContent {
     Signal,
     Read,
     RunInfo,
+    ReadIdIndex,
     Other,
 }
 
@@ -76,6 +134,7 @@ impl TableContent {
             TableContent::Signal => ContentType::SignalTable,
             TableContent::Read => ContentType::ReadsTable,
             TableContent::RunInfo => ContentType::RunInfoTable,
+            TableContent::ReadIdIndex => ContentType::ReadIdIndex,
             TableContent::Other => ContentType::OtherIndex,
         }
     }
@@ -85,6 +144,12 @@ pub trait IntoTable {
     type Schema: TableSchema;
     fn as_dataframe(&self) -> &DataFrame;
     fn content_type() -> TableContent;
+
+    /// Name stored in the table's schema metadata, used to tell user tables
+    /// apart.
+    fn table_name(&self) -> Option<&str> {
+        None
+    }
 }
 
 impl IntoTable for SignalDataFrame {
@@ -119,6 +184,186 @@ impl IntoTable for RunInfoDataFrame {
     }
 }
 
+impl IntoTable for ReadIdIndexDataFrame {
+    type Schema = ReadIdIndexSchema;
+    fn as_dataframe(&self) -> &DataFrame {
+        &self.0
+    }
+
+    fn content_type() -> TableContent {
+        TableContent::ReadIdIndex
+    }
+}
+
+impl IntoTable for OtherDataFrame {
+    type Schema = OtherSchema;
+    fn as_dataframe(&self) -> &DataFrame {
+        &self.df
+    }
+
+    fn content_type() -> TableContent {
+        TableContent::Other
+    }
+
+    fn table_name(&self) -> Option<&str> {
+        Some(&self.name)
+    }
+}
+
+/// Version of the POD5 table schemas to write.
+///
+/// Files older than v3 have no Run Info table. Their Read table stores the
+/// pore, calibration, end reason and run info of each read as dictionaries
+/// of structs instead.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum SchemaVersion {
+    /// Read table without the columns added in v1
+    V0,
+    /// Adds `num_minknow_events`, the tracked and predicted scaling and the
+    /// mux change columns to the Read table
+    V1,
+    /// Adds `num_samples` to the Read table
+    V2,
+    /// Separate Run Info table, and pore, calibration and end reason columns
+    /// stored directly in the Read table
+    #[default]
+    V3,
+}
+
+impl SchemaVersion {
+    /// The last pod5 release that wrote this version of the schemas.
+    pub fn pod5_version(&self) -> &'static str {
+        match self {
+            SchemaVersion::V0 => "0.0.16",
+            SchemaVersion::V1 => "0.0.29",
+            SchemaVersion::V2 => "0.0.32",
+            SchemaVersion::V3 => "0.0.40",
+        }
+    }
+}
+
+/// Identifiers, provenance strings and schema version written to a POD5
+/// file.
+///
+/// By default the file_identifier and section marker are random, so writing
+/// the same data twice gives different files. Setting both, or deriving them
+/// from a seed, makes the output reproducible byte for byte.
+#[derive(Debug, Clone)]
+pub struct WriterOptions {
+    file_identifier: Uuid,
+    section_marker: Uuid,
+    software: String,
+    pod5_version: String,
+    schema_version: SchemaVersion,
+}
+
+impl Default for WriterOptions {
+    fn default() -> Self {
+        let schema_version = SchemaVersion::default();
+        Self {
+            file_identifier: Uuid::new_v4(),
+            section_marker: Uuid::new_v4(),
+            software: SOFTWARE.to_string(),
+            pod5_version: schema_version.pod5_version().to_string(),
+            schema_version,
+        }
+    }
+}
+
+impl WriterOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Options with the file_identifier and section marker generated from
+    /// `seed`, so the same seed always gives the same identifiers.
+    pub fn from_seed(seed: u64) -> Self {
+        let mut state = seed;
+        let mut uuid = || {
+            let mut bytes = [0u8; 16];
+            for half in bytes.chunks_exact_mut(8) {
+                half.copy_from_slice(&splitmix64(&mut state).to_le_bytes());
+            }
+            uuid::Builder::from_random_bytes(bytes).into_uuid()
+        };
+        Self {
+            file_identifier: uuid(),
+            section_marker: uuid(),
+            ..Self::default()
+        }
+    }
+
+    /// Identifier stored in the footer and in every table's schema metadata.
+    pub fn with_file_identifier(mut self, file_identifier: Uuid) -> Self {
+        self.file_identifier = file_identifier;
+        self
+    }
+
+    /// Marker written between the sections of the file.
+    pub fn with_section_marker(mut self, section_marker: Uuid) -> Self {
+        self.section_marker = section_marker;
+        self
+    }
+
+    /// Name of the software writing the file, `pod5-rs` by default.
+    pub fn with_software<S: Into<String>>(mut self, software: S) -> Self {
+        self.software = software.into();
+        self
+    }
+
+    /// POD5 version written to the footer and schema metadata.
+    pub fn with_pod5_version<S: Into<String>>(mut self, pod5_version: S) -> Self {
+        self.pod5_version = pod5_version.into();
+        self
+    }
+
+    /// Write tables using the schemas of `schema_version`, for tools that
+    /// can't open newer files. Also sets the POD5 version to the last release
+    /// using those schemas, which [`WriterOptions::with_pod5_version`] can
+    /// override afterwards.
+    ///
+    /// Tables are always given to the [`Writer`] in the current layout. When
+    /// writing files older than v3, the Run Info table must be written before
+    /// the Read table, and is stored in it instead of as a table of its own.
+    /// The Read table is also kept in memory until its guard is finished,
+    /// since its dictionaries are shared by every record batch.
+    pub fn with_schema_version(mut self, schema_version: SchemaVersion) -> Self {
+        self.schema_version = schema_version;
+        self.pod5_version = schema_version.pod5_version().to_string();
+        self
+    }
+
+    pub fn file_identifier(&self) -> Uuid {
+        self.file_identifier
+    }
+
+    pub fn section_marker(&self) -> Uuid {
+        self.section_marker
+    }
+
+    pub fn software(&self) -> &str {
+        &self.software
+    }
+
+    pub fn pod5_version(&self) -> &str {
+        &self.pod5_version
+    }
+
+    pub fn schema_version(&self) -> SchemaVersion {
+        self.schema_version
+    }
+}
+
+/// Next value of a SplitMix64 generator, enough to spread a seed over the
+/// bits of a UUID.
+fn splitmix64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9e3779b97f4a7c15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
+}
+
 pub struct Writer<W>
 where
     W: Write + Seek,
@@ -127,19 +372,180 @@ where
     writer: W,
     section_marker: Uuid,
     file_identifier: Uuid,
+    software: String,
+    pod5_version: String,
     tables: Vec<TableInfo>,
     contents_writtens: HashSet<ContentType>,
     // footer_written: bool,
     metadata: Arc<Metadata>,
+    /// read_ids of the Read table in row order, collected when a read_id
+    /// index should be written on finish.
+    read_id_index: Option<Vec<Uuid>>,
+    /// Number of rows per record batch, when batches should be rechunked
+    /// instead of written as they are given.
+    batch_size: Option<usize>,
+    /// Temporary file renamed into place on finish, from
+    /// [`Writer::create_atomic`].
+    atomic: Option<AtomicTarget>,
+    /// File the staged tables are copied into on finish, from
+    /// [`Writer::open_append`].
+    append: Option<AppendTarget>,
+    /// Write a footer for the completed tables if dropped without finishing
+    finalize_on_drop: bool,
+    finished: bool,
+    schema_version: SchemaVersion,
+    /// Run infos to store in the Read table, when writing files older than v3
+    legacy_run_infos: Option<DataFrame>,
+}
+
+/// A POD5 file opened with [`Writer::open_append`].
+///
+/// New tables are written to an [`AppendStage`] next to `file`, so `file`
+/// keeps its original footer until the Writer finishes.
+struct AppendTarget {
+    /// Handle to the temporary file, used to copy the staged bytes
+    staged: File,
+    temp: PathBuf,
+    file: File,
+    /// Offset of the original footer
+    start: u64,
+    /// Length of the original footer, copied to the start of `staged`
+    saved: u64,
+}
+
+impl AppendTarget {
+    /// Replace the original footer with the staged tables and new footer,
+    /// and remove the temporary file.
+    ///
+    /// If that fails, the original footer is written back and the temporary
+    /// file is kept.
+    fn persist(mut self) -> Result<(), WriteError> {
+        let res = self
+            .staged
+            .sync_all()
+            .and_then(|_| self.staged.seek(SeekFrom::Start(self.saved)))
+            .and_then(|_| self.file.seek(SeekFrom::Start(self.start)))
+            .and_then(|_| std::io::copy(&mut self.staged, &mut self.file))
+            .and_then(|_| self.file.stream_position())
+            .and_then(|end| self.file.set_len(end))
+            .and_then(|_| self.file.sync_all());
+        if let Err(e) = res {
+            let _ = self.restore();
+            return Err(WriteError::FailedToPersist(e));
+        }
+        let _ = fs::remove_file(&self.temp);
+        Ok(())
+    }
+
+    /// Copy the original footer back over whatever was written in its place.
+    fn restore(&mut self) -> std::io::Result<()> {
+        self.staged.seek(SeekFrom::Start(0))?;
+        self.file.seek(SeekFrom::Start(self.start))?;
+        std::io::copy(&mut (&self.staged).take(self.saved), &mut self.file)?;
+        self.file.set_len(self.start + self.saved)?;
+        self.file.sync_all()
+    }
+}
+
+/// Temporary file next to the file being appended to, that a Writer from
+/// [`Writer::open_append`] writes the new tables to.
+///
+/// It starts with a copy of the original footer, followed by the new tables
+/// and footer. Seeking is relative to the original file, so the new tables
+/// start at the offset of the original footer.
+pub struct AppendStage {
+    file: File,
+    /// Offset of the original footer in the original file
+    start: u64,
+    /// Length of the copy of the original footer
+    saved: u64,
+}
+
+impl Write for AppendStage {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.file.write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.file.flush()
+    }
+}
+
+impl Seek for AppendStage {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        let pos = match pos {
+            SeekFrom::Start(pos) => {
+                let pos = (pos + self.saved).checked_sub(self.start).ok_or_else(|| {
+                    std::io::Error::new(
+                        std::io::ErrorKind::InvalidInput,
+                        "seek before the end of the appended file",
+                    )
+                })?;
+                SeekFrom::Start(pos)
+            }
+            pos => pos,
+        };
+        Ok(self.file.seek(pos)? + self.start - self.saved)
+    }
+}
+
+/// A temporary sibling of `path`, hidden and unique to this Writer.
+fn temp_path(path: &Path) -> PathBuf {
+    let file_name = path
+        .file_name()
+        .map(|name| name.to_string_lossy())
+        .unwrap_or_default();
+    path.with_file_name(format!(".{file_name}.{}.tmp", Uuid::new_v4()))
+}
+
+/// A temporary sibling of `path` that a [`Writer`] writes to.
+struct AtomicTarget {
+    /// Handle to the temporary file, used to sync it to disk
+    file: File,
+    temp: PathBuf,
+    path: PathBuf,
+}
+
+impl AtomicTarget {
+    /// Sync the temporary file and rename it to the final path, replacing any
+    /// file already there.
+    fn persist(self) -> Result<(), WriteError> {
+        let res = self
+            .file
+            .sync_all()
+            .and_then(|_| fs::rename(&self.temp, &self.path));
+        if let Err(e) = res {
+            let _ = fs::remove_file(&self.temp);
+            return Err(WriteError::FailedToPersist(e));
+        }
+        // Make sure the rename itself is on disk
+        #[cfg(unix)]
+        if let Some(parent) = self.path.parent() {
+            let parent = if parent.as_os_str().is_empty() {
+                Path::new(".")
+            } else {
+                parent
+            };
+            File::open(parent)
+                .and_then(|dir| dir.sync_all())
+                .map_err(WriteError::FailedToPersist)?;
+        }
+        Ok(())
+    }
 }
 
 impl<W: Write + Seek> Writer<W> {
-    pub(crate) fn new(writer: W) -> Self {
-        let section_marker = Uuid::new_v4();
-        let file_identifier = Uuid::new_v4();
+    pub(crate) fn new(writer: W, options: WriterOptions) -> Self {
+        let WriterOptions {
+            file_identifier,
+            section_marker,
+            software,
+            pod5_version,
+            schema_version,
+        } = options;
         let mut metadata = Metadata::new();
-        metadata.insert("MINKNOW:pod5_version".into(), POD5_VERSION.into());
-        metadata.insert("MINKNOW:software".into(), SOFTWARE.into());
+        metadata.insert("MINKNOW:pod5_version".into(), pod5_version.as_str().into());
+        metadata.insert("MINKNOW:software".into(), software.as_str().into());
         metadata.insert(
             "MINKNOW:file_identifier".into(),
             file_identifier.to_string().into(),
@@ -153,7 +559,17 @@ impl<W: Write + Seek> Writer<W> {
             contents_writtens: HashSet::new(),
             // footer_written: false,
             file_identifier,
+            software,
+            pod5_version,
             metadata,
+            read_id_index: None,
+            batch_size: None,
+            append: None,
+            atomic: None,
+            finalize_on_drop: false,
+            finished: false,
+            schema_version,
+            legacy_run_infos: None,
         }
     }
 
@@ -176,13 +592,69 @@ impl<W: Write + Seek> Writer<W> {
     /// This will write the POD5 signature and section marker to the file.
     /// SAFETY: This will rewind the underlying writer, so it will be safe
     /// writing to a file that has already been written to.
-    pub fn from_writer(mut writer: W) -> Result<Self, WriteError> {
+    pub fn from_writer(writer: W) -> Result<Self, WriteError> {
+        Self::from_writer_with_options(writer, WriterOptions::default())
+    }
+
+    /// Build and initialize a new POD5 Writer using `options` for the file's
+    /// identifiers and provenance. See [`Writer::from_writer`].
+    pub fn from_writer_with_options(
+        mut writer: W,
+        options: WriterOptions,
+    ) -> Result<Self, WriteError> {
         writer.rewind().map_err(WriteError::FailedToRewind)?;
-        let mut w = Self::new(writer);
+        let mut w = Self::new(writer, options);
         w.init()?;
         Ok(w)
     }
 
+    /// Stop writing without finishing the file.
+    ///
+    /// For a Writer from [`Writer::open_append`] or [`Writer::create_atomic`],
+    /// the temporary file is removed and the file at the destination is left
+    /// as it was. Otherwise the file is left without a footer, and only
+    /// [`recover`](crate::recover::recover) can read it.
+    ///
+    /// Dropping an unfinished Writer does the same, unless
+    /// [`Writer::finalize_on_drop`] was used.
+    pub fn abort(mut self) -> Result<(), WriteError> {
+        self.finished = true;
+        self.discard();
+        Ok(())
+    }
+
+    /// Move the finished file into place, for a Writer writing to a
+    /// temporary file.
+    fn persist(&mut self) -> Result<(), WriteError> {
+        if let Some(target) = self.atomic.take() {
+            target.persist()?;
+        }
+        if let Some(target) = self.append.take() {
+            target.persist()?;
+        }
+        Ok(())
+    }
+
+    /// Remove the temporary file of an unfinished Writer.
+    fn discard(&mut self) {
+        if let Some(target) = self.atomic.take() {
+            let _ = fs::remove_file(&target.temp);
+        }
+        if let Some(target) = self.append.take() {
+            let _ = fs::remove_file(&target.temp);
+        }
+    }
+
+    /// When the Writer is dropped without calling [`Writer::finish`], for
+    /// example because of an error or a panic, write a footer listing the
+    /// tables that were completed instead of discarding the file.
+    ///
+    /// A table whose guard wasn't finished is left out of the footer.
+    pub fn finalize_on_drop(mut self) -> Self {
+        self.finalize_on_drop = true;
+        self
+    }
+
     pub(crate) fn init(&mut self) -> Result<(), WriteError> {
         self.write_signature()?;
         self.write_section_marker()?;
@@ -199,29 +671,73 @@ impl<W: Write + Seek> Writer<W> {
             .stream_position()
             .map_err(WriteError::StreamPositionError)?;
         // Padding the footer to 8-byte boundary
-        let padding = 8 - (new_position % 8);
-        self.write_all(&vec![0u8; padding as usize]).unwrap();
-        self.write_section_marker().unwrap();
+        let padding = (8 - new_position % 8) % 8;
+        self.write_all(&vec![0u8; padding as usize])
+            .map_err(WriteError::FailedToWriteSectionMarker)?;
+        self.write_section_marker()?;
         let offset = self.position as i64;
         let length = new_position as i64 - self.position as i64;
         self.tables
             .push(TableInfo::new(offset, length, content_type));
-        self.position = self.writer.stream_position().unwrap();
+        if content_type != ContentType::OtherIndex {
+            self.contents_writtens.insert(content_type);
+        }
+        self.position = self
+            .writer
+            .stream_position()
+            .map_err(WriteError::StreamPositionError)?;
         Ok(())
     }
 
+    /// Write an embedded read_id index when finishing the file, so that
+    /// readers can look up reads without scanning the Read table.
+    ///
+    /// The index is built from the read_ids of every Read table batch written
+    /// after this is called.
+    pub fn with_read_id_index(mut self) -> Self {
+        self.read_id_index = Some(Vec::new());
+        self
+    }
+
+    /// Rechunk every table into record batches of `rows` rows, the last batch
+    /// of a table holding whatever is left over.
+    ///
+    /// Rows are buffered across calls to [`TableWriteGuard::write_batch`], so
+    /// the batch sizes don't depend on the size of the DataFrames written. The
+    /// official writer uses roughly 1000 reads per batch. By default, every
+    /// chunk of a DataFrame is written as its own record batch.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `rows` is 0.
+    pub fn with_batch_size(mut self, rows: usize) -> Self {
+        assert!(rows > 0, "batch size must be at least one row");
+        self.batch_size = Some(rows);
+        self
+    }
+
     /// Write the flatbuffers footer and last signature bits to finish writing
     /// the file.
     pub fn finish(mut self) -> Result<(), WriteError> {
         self._finish()?;
-        Ok(())
+        self.writer
+            .flush()
+            .map_err(WriteError::FailedToWriteFooter)?;
+        self.persist()
     }
 
     pub(crate) fn _finish(&mut self) -> Result<(), WriteError> {
+        if let Some(read_ids) = self.read_id_index.take()
+            && !read_ids.is_empty()
+        {
+            let index = ReadIdIndex::from_read_ids(read_ids).to_dataframe()?;
+            self.with_guard(|g| g.write_batch(&index))?;
+        }
         self.write_footer_magic()?;
         self.write_footer()?;
         self.write_section_marker()?;
         self.write_signature()?;
+        self.finished = true;
         Ok(())
     }
 
@@ -248,9 +764,29 @@ impl<W: Write + Seek> Writer<W> {
 
     pub fn guard<T: IntoTable>(&mut self) -> TableWriteGuard<'_, W, T> {
         let metadata = self.metadata.clone();
+        let read_ids = match T::content_type() {
+            TableContent::Read if self.read_id_index.is_some() => Some(Vec::new()),
+            _ => None,
+        };
+        let batch_size = self.batch_size;
+        let legacy = match T::content_type() {
+            _ if self.schema_version == SchemaVersion::V3 => None,
+            TableContent::RunInfo => Some(LegacyTable::RunInfo(None)),
+            TableContent::Read => Some(LegacyTable::Read {
+                version: self.schema_version,
+                run_infos: self.legacy_run_infos.clone(),
+                chunks: Vec::new(),
+            }),
+            _ => None,
+        };
         TableWriteGuard {
             inner: Some(TableWriter::PreInit(self)),
             metadata,
+            read_ids,
+            batch_size,
+            pending: None,
+            table_name: None,
+            legacy,
             table: PhantomData,
         }
     }
@@ -305,13 +841,218 @@ impl<W: Write + Seek> Writer<W> {
     fn build_footer(&self) -> Vec<u8> {
         pod5_format::FooterBuilder::new(
             self.file_identifier.to_string(),
-            SOFTWARE.to_string(),
-            POD5_VERSION.to_string(),
+            self.software.clone(),
+            self.pod5_version.clone(),
         )
         .build_footer(&self.tables)
     }
 }
 
+impl Writer<File> {
+    /// Create a POD5 file at `path` that only appears once it is complete.
+    ///
+    /// The file is written to a temporary file next to `path`, which is
+    /// synced to disk and renamed to `path` when [`Writer::finish`] succeeds.
+    /// If the Writer is dropped or aborted before then, the temporary file is
+    /// removed and `path` is left untouched. A process that gets killed can
+    /// still leave the temporary file behind, but never a partial file at
+    /// `path`.
+    pub fn create_atomic<P: AsRef<Path>>(path: P) -> Result<Self, WriteError> {
+        Self::create_atomic_with_options(path, WriterOptions::default())
+    }
+
+    /// Create a POD5 file at `path` that only appears once it is complete,
+    /// using `options` for the file's identifiers and provenance. See
+    /// [`Writer::create_atomic`].
+    pub fn create_atomic_with_options<P: AsRef<Path>>(
+        path: P,
+        options: WriterOptions,
+    ) -> Result<Self, WriteError> {
+        let path = path.as_ref().to_path_buf();
+        let temp = temp_path(&path);
+        let file = File::options()
+            .read(true)
+            .write(true)
+            .create_new(true)
+            .open(&temp)
+            .map_err(WriteError::FailedToCreateTempFile)?;
+        let target = AtomicTarget {
+            file: file
+                .try_clone()
+                .map_err(WriteError::FailedToCreateTempFile)?,
+            temp,
+            path,
+        };
+        let mut writer = Self::new(file, options);
+        writer.atomic = Some(target);
+        writer.init()?;
+        Ok(writer)
+    }
+}
+
+impl Writer<AppendStage> {
+    /// Open the finished POD5 file at `path` to add more tables to it.
+    ///
+    /// The existing tables and file_identifier are kept, so
+    /// [`Writer::finish`] writes a footer listing both the existing and the
+    /// new tables in place of the original footer. The new footer keeps the
+    /// file's software and POD5 version, and new tables use the schema
+    /// version of its Read table.
+    ///
+    /// Only tables of a type the file doesn't already have can be added
+    /// through [`Writer::with_guard`], so this is mainly for adding user
+    /// tables or a read_id index. Until the Writer finishes, new tables are
+    /// staged in a temporary file next to `path` and the file isn't
+    /// modified, so if appending fails or the process dies, the file still
+    /// opens with the tables it had before. If the staged tables can't be
+    /// copied into place on finish, the original footer is written back and
+    /// the temporary file, which also holds a copy of it, is kept.
+    pub fn open_append<P: AsRef<Path>>(path: P) -> Result<Self, WriteError> {
+        let path = path.as_ref();
+        let mut file = File::open(path).map_err(WriteError::FailedToOpenForAppend)?;
+        let footer = ParsedFooter::read_footer(&mut file)?.info()?;
+        file.rewind().map_err(WriteError::FailedToRewind)?;
+        let schema_version = Reader::from_reader(file)
+            .ok()
+            .and_then(|mut reader| Some(legacy::schema_version(reader.read_dfs().ok()?.fields())))
+            .unwrap_or_default();
+        let mut options = WriterOptions::default().with_schema_version(schema_version);
+        if let Some(software) = footer.software() {
+            options = options.with_software(software);
+        }
+        if let Some(pod5_version) = footer.pod5_version() {
+            options = options.with_pod5_version(pod5_version);
+        }
+        Self::open_append_with_options(path, options)
+    }
+
+    /// Open the finished POD5 file at `path` to add more tables to it, using
+    /// the software, POD5 version and schema version from `options` instead
+    /// of the file's. The file_identifier and section marker are always the
+    /// file's own. See [`Writer::open_append`].
+    pub fn open_append_with_options<P: AsRef<Path>>(
+        path: P,
+        options: WriterOptions,
+    ) -> Result<Self, WriteError> {
+        let path = path.as_ref();
+        let mut file = File::options()
+            .read(true)
+            .write(true)
+            .open(path)
+            .map_err(WriteError::FailedToOpenForAppend)?;
+        let footer = ParsedFooter::read_footer(&mut file)?.info()?;
+        let file_len = file
+            .seek(SeekFrom::End(0))
+            .map_err(WriteError::StreamPositionError)?;
+
+        // The file ends with the footer magic, footer, footer length, section
+        // marker and signature.
+        let mut section_marker = [0u8; 16];
+        let mut footer_len = [0u8; 8];
+        file.seek(SeekFrom::Start(FILE_SIGNATURE.len() as u64))
+            .and_then(|_| file.read_exact(&mut section_marker))
+            .and_then(|_| file.seek(SeekFrom::End(-32)))
+            .and_then(|_| file.read_exact(&mut footer_len))
+            .map_err(WriteError::FailedToOpenForAppend)?;
+        let footer_start = (file_len - 32)
+            .checked_sub(u64::from_le_bytes(footer_len) + FOOTER_MAGIC.len() as u64)
+            .ok_or(FormatError::from(FooterError::InvalidFooterLength(
+                i64::from_le_bytes(footer_len),
+            )))?;
+        // The footer normally follows the section marker ending the last
+        // table, but files left by an interrupted writer may not have it.
+        let mut last_marker = [0u8; 16];
+        let mut footer_magic = [0u8; FOOTER_MAGIC.len()];
+        file.seek(SeekFrom::Start(footer_start.saturating_sub(16)))
+            .and_then(|_| file.read_exact(&mut last_marker))
+            .and_then(|_| file.read_exact(&mut footer_magic))
+            .map_err(WriteError::FailedToOpenForAppend)?;
+        if footer_magic != FOOTER_MAGIC {
+            return Err(WriteError::FailedToOpenForAppend(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "footer magic not found before the footer",
+            )));
+        }
+
+        // Keep a copy of the original footer in the stage file, so it can be
+        // written back if replacing it fails.
+        let saved = file_len - footer_start;
+        let temp = temp_path(path);
+        let mut staged = File::options()
+            .read(true)
+            .write(true)
+            .create_new(true)
+            .open(&temp)
+            .map_err(WriteError::FailedToCreateTempFile)?;
+        let target = file
+            .seek(SeekFrom::Start(footer_start))
+            .and_then(|_| std::io::copy(&mut (&file).take(saved), &mut staged))
+            .and_then(|_| staged.try_clone())
+            .map(|handle| AppendTarget {
+                staged: handle,
+                temp: temp.clone(),
+                file,
+                start: footer_start,
+                saved,
+            });
+        let target = match target {
+            Ok(target) => target,
+            Err(e) => {
+                let _ = fs::remove_file(&temp);
+                return Err(WriteError::FailedToCreateTempFile(e));
+            }
+        };
+        let staged = AppendStage {
+            file: staged,
+            start: footer_start,
+            saved,
+        };
+
+        let file_identifier = footer
+            .file_identifier()
+            .and_then(|id| Uuid::parse_str(id).ok())
+            .unwrap_or_default();
+        let options = options
+            .with_file_identifier(file_identifier)
+            .with_section_marker(Uuid::from_bytes(section_marker));
+        let mut writer = Self::new(staged, options);
+        writer.append = Some(target);
+        writer
+            .writer
+            .seek(SeekFrom::Start(footer_start))
+            .map_err(WriteError::StreamPositionError)?;
+        if last_marker != section_marker {
+            writer.write_section_marker()?;
+        }
+        writer.position = writer
+            .writer
+            .stream_position()
+            .map_err(WriteError::StreamPositionError)?;
+        writer.contents_writtens = footer
+            .tables()
+            .iter()
+            .map(|t| t.content_type())
+            .filter(|&c| c != ContentType::OtherIndex)
+            .collect();
+        writer.tables = footer.tables().to_vec();
+        Ok(writer)
+    }
+}
+
+impl<W: Write + Seek> Drop for Writer<W> {
+    fn drop(&mut self) {
+        if self.finished {
+            return;
+        }
+        if self.finalize_on_drop && self._finish().is_ok() {
+            let _ = self.writer.flush();
+            let _ = self.persist();
+            return;
+        }
+        self.discard();
+    }
+}
+
 impl<W: Write + Seek> Write for Writer<W> {
     fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
         self.writer.write(buf)
@@ -342,6 +1083,20 @@ where
     PostInit(FileWriter<&'a mut Writer<W>>),
 }
 
+/// How a table is written to a file older than v3.
+enum LegacyTable {
+    /// Run infos held back until the Read table is written
+    RunInfo(Option<DataFrame>),
+    /// Read table chunks held back until the table is finished, so that its
+    /// dictionaries can be built from every row, along with the run infos
+    /// written so far
+    Read {
+        version: SchemaVersion,
+        run_infos: Option<DataFrame>,
+        chunks: Vec<DataFrame>,
+    },
+}
+
 /// An scoped guard for writing a specific table type to the POD5 file.
 /// This allows for writing a table iteratively, and ensures that only one of
 /// each non-OtherIndex tables have been written.
@@ -358,15 +1113,154 @@ where
 {
     inner: Option<TableWriter<'a, W>>,
     metadata: Arc<Metadata>,
+    /// read_ids written so far, when the Writer is building a read_id index
+    read_ids: Option<Vec<Uuid>>,
+    /// Rows per record batch, see [`Writer::with_batch_size`]
+    batch_size: Option<usize>,
+    /// Rows waiting for a full batch when rechunking
+    pending: Option<DataFrame>,
+    /// Name from the first batch written, for user tables
+    table_name: Option<PlSmallStr>,
+    /// Set when writing a Run Info or Read table older than v3
+    legacy: Option<LegacyTable>,
     table: PhantomData<T>,
 }
 
-fn pod5_metadata<S: Into<PlSmallStr>>(file_identifier: S) -> Arc<BTreeMap<PlSmallStr, PlSmallStr>> {
-    let mut metadata = Metadata::new();
-    metadata.insert("MINKNOW:pod5_version".into(), POD5_VERSION.into());
-    metadata.insert("MINKNOW:software".into(), SOFTWARE.into());
-    metadata.insert("MINKNOW:file_identifier".into(), file_identifier.into());
-    Arc::new(metadata)
+/// Polars types accepted for a column with the Arrow type `dtype`. Columns
+/// of any other type are cast to the first one when possible.
+fn accepted_dtypes(dtype: &ArrowDataType) -> Vec<DataType> {
+    match dtype {
+        ArrowDataType::Extension(ext) if ext.name == "minknow.uuid" => vec![DataType::String],
+        // Decompressed signal, or signal that is already VBZ compressed
+        ArrowDataType::Extension(ext) if ext.name == "minknow.vbz" => {
+            vec![DataType::List(Box::new(DataType::Int16)), DataType::Binary]
+        }
+        ArrowDataType::Dictionary(..) => {
+            vec![DataType::Categorical(None, CategoricalOrdering::Physical)]
+        }
+        ArrowDataType::Map(..) => vec![DataType::List(Box::new(DataType::Struct(vec![
+            Field::new("key".into(), DataType::String),
+            Field::new("value".into(), DataType::String),
+        ])))],
+        dtype => vec![DataType::from_arrow_dtype(dtype)],
+    }
+}
+
+/// Whether a column of type `from` can be cast to `to` when writing.
+///
+/// Numeric and timestamp columns are only cast when every value fits
+/// exactly, so columns that would be truncated or rounded are reported as the
+/// wrong type.
+fn coercible(from: &DataType, to: &DataType) -> bool {
+    match (from, to) {
+        (DataType::Null, _) => true,
+        (from, to) if from.is_primitive_numeric() && to.is_primitive_numeric() => widens(from, to),
+        (DataType::String, DataType::Categorical(..)) => true,
+        (DataType::Categorical(..) | DataType::Enum(..), DataType::String) => true,
+        (DataType::Datetime(from_unit, from_tz), DataType::Datetime(to_unit, to_tz)) => {
+            // Only units at least as fine keep every value, and timestamps
+            // without a timezone are taken to be in the schema's
+            precision(to_unit) >= precision(from_unit) && (from_tz.is_none() || from_tz == to_tz)
+        }
+        _ => false,
+    }
+}
+
+/// Whether every value of numeric type `from` is exactly representable in
+/// `to`.
+fn widens(from: &DataType, to: &DataType) -> bool {
+    let (Some(from_bits), Some(to_bits)) = (numeric_bits(from), numeric_bits(to)) else {
+        return from == to;
+    };
+    if to.is_float() {
+        // Integers have to fit in the 24 bit mantissa of f32 or 53 bits of f64
+        let mantissa = if to_bits == 32 { 24 } else { 53 };
+        return if from.is_float() {
+            to_bits >= from_bits
+        } else {
+            from_bits < mantissa
+        };
+    }
+    if from.is_float() {
+        return false;
+    }
+    match (from.is_signed_integer(), to.is_signed_integer()) {
+        (true, true) | (false, false) => to_bits >= from_bits,
+        (false, true) => to_bits > from_bits,
+        (true, false) => false,
+    }
+}
+
+fn precision(unit: &TimeUnit) -> u8 {
+    match unit {
+        TimeUnit::Milliseconds => 0,
+        TimeUnit::Microseconds => 1,
+        TimeUnit::Nanoseconds => 2,
+    }
+}
+
+fn numeric_bits(dtype: &DataType) -> Option<u32> {
+    match dtype {
+        DataType::Int8 | DataType::UInt8 => Some(8),
+        DataType::Int16 | DataType::UInt16 => Some(16),
+        DataType::Int32 | DataType::UInt32 | DataType::Float32 => Some(32),
+        DataType::Int64 | DataType::UInt64 | DataType::Float64 => Some(64),
+        _ => None,
+    }
+}
+
+/// Check the columns of `df` against `schema`, casting columns of compatible
+/// types and putting them in schema order.
+///
+/// Tables without a fixed schema, like user tables, are returned as they are.
+fn conform_to_schema<'a>(
+    df: &'a DataFrame,
+    schema: &ArrowSchema,
+) -> Result<Cow<'a, DataFrame>, WriteError> {
+    if schema.is_empty() {
+        return Ok(Cow::Borrowed(df));
+    }
+    if let Some(column) = df
+        .get_column_names()
+        .into_iter()
+        .find(|name| !schema.contains(name))
+    {
+        return Err(WriteError::UnexpectedColumn(column.to_string()));
+    }
+
+    let mut columns = Vec::with_capacity(schema.len());
+    for (name, field) in schema.iter() {
+        let accepted = accepted_dtypes(&field.dtype);
+        let Ok(column) = df.column(name) else {
+            return Err(WriteError::MissingColumn {
+                column: name.to_string(),
+                expected: Box::new(accepted[0].clone()),
+            });
+        };
+        let column =
+            match column.dtype() {
+                DataType::Categorical(..) if matches!(accepted[0], DataType::Categorical(..)) => {
+                    column.clone()
+                }
+                dtype if accepted.contains(dtype) => column.clone(),
+                dtype if coercible(dtype, &accepted[0]) => column
+                    .strict_cast(&accepted[0])
+                    .map_err(|_| WriteError::ColumnType {
+                        column: name.to_string(),
+                        expected: Box::new(accepted[0].clone()),
+                        actual: Box::new(dtype.clone()),
+                    })?,
+                dtype => {
+                    return Err(WriteError::ColumnType {
+                        column: name.to_string(),
+                        expected: Box::new(accepted[0].clone()),
+                        actual: Box::new(dtype.clone()),
+                    });
+                }
+            };
+        columns.push(column);
+    }
+    Ok(Cow::Owned(DataFrame::new(columns)?))
 }
 
 impl<'a, W, T> TableWriteGuard<'a, W, T>
@@ -375,13 +1269,18 @@ where
     T: IntoTable,
 {
     pub fn new(writer: &'a mut Writer<W>) -> Result<Self, WriteError> {
-        let metadata = pod5_metadata(writer.file_identifier.to_string());
+        let metadata = writer.metadata.clone();
         let mut writer = FileWriter::new(writer, T::Schema::as_schema(), None, Default::default());
         writer.set_custom_schema_metadata(metadata.clone());
         writer.start()?;
         Ok(TableWriteGuard {
             inner: Some(TableWriter::PostInit(writer)),
-            metadata: metadata,
+            metadata,
+            read_ids: None,
+            batch_size: None,
+            pending: None,
+            table_name: None,
+            legacy: None,
             table: PhantomData,
         })
     }
@@ -427,19 +1326,101 @@ where
     //     Ok(())
     // }
 
+    /// Write every row of `df` to the table.
+    ///
+    /// Each chunk of `df` is written as a record batch, unless the Writer was
+    /// built with [`Writer::with_batch_size`], in which case rows are held
+    /// back until a full batch is available.
+    ///
+    /// Columns are checked against the table's schema. Columns of compatible
+    /// types are cast, for example u32 to u64 or strings to dictionaries, and
+    /// other mismatches, including numeric casts that could lose precision
+    /// like f32 to u32, return an error before anything is written.
+    ///
+    /// Signal given as binary, like the DataFrames from
+    /// [`SignalDataFrameIter::raw`](crate::dataframe::SignalDataFrameIter::raw),
+    /// is taken to be VBZ compressed already and copied as is. Only signal
+    /// given as i16 lists is compressed.
     pub fn write_batch(&mut self, df: &T) -> Result<(), WriteError> {
-        let batch = df
-            .as_dataframe()
-            .iter_chunks(CompatLevel::newest(), false)
-            .next()
-            .unwrap();
-        let batch = record_batch_to_compat(batch).unwrap();
-        let schema = Arc::new(batch.schema().clone());
+        let data = conform_to_schema(df.as_dataframe(), &T::Schema::as_schema())?;
+        if let Some(read_ids) = self.read_ids.as_mut() {
+            for read_id in data.column("read_id")?.str()? {
+                let read_id = read_id.unwrap_or_default();
+                let uuid = Uuid::parse_str(read_id)
+                    .map_err(|_| WriteError::InvalidReadId(read_id.to_string()))?;
+                read_ids.push(uuid);
+            }
+        }
+        if self.table_name.is_none() {
+            self.table_name = df.table_name().map(PlSmallStr::from);
+        }
+        if let Some(LegacyTable::RunInfo(run_infos)) = &mut self.legacy {
+            match run_infos {
+                Some(run_infos) => {
+                    run_infos.vstack_mut(&data)?;
+                }
+                None => *run_infos = Some(data.into_owned()),
+            }
+            return Ok(());
+        }
+
+        let Some(batch_size) = self.batch_size else {
+            return self.write_chunks(&data);
+        };
+        let mut pending = match self.pending.take() {
+            Some(mut pending) => {
+                pending.vstack_mut(&data)?;
+                pending
+            }
+            None => data.into_owned(),
+        };
+        while pending.height() >= batch_size {
+            let (mut batch, rest) = pending.split_at(batch_size as i64);
+            batch.rechunk_mut();
+            self.write_chunks(&batch)?;
+            pending = rest;
+        }
+        self.pending = (pending.height() > 0).then_some(pending);
+        Ok(())
+    }
+
+    /// Write each chunk of `df` as a record batch.
+    fn write_chunks(&mut self, df: &DataFrame) -> Result<(), WriteError> {
+        if df.should_rechunk() {
+            let mut df = df.clone();
+            df.align_chunks();
+            return self.write_chunks(&df);
+        }
+        if let Some(LegacyTable::Read { chunks, .. }) = &mut self.legacy {
+            chunks.extend(df.clone().split_chunks().filter(|c| c.height() > 0));
+            return Ok(());
+        }
+        for batch in df.iter_chunks(CompatLevel::newest(), false) {
+            self.write_record_batch(record_batch_to_compat(batch)?)?;
+        }
+        Ok(())
+    }
 
+    /// Write a record batch of POD5 types, starting the table if needed.
+    fn write_record_batch(
+        &mut self,
+        batch: RecordBatchT<Box<dyn Array>>,
+    ) -> Result<(), WriteError> {
         let mut w = match self.inner.take() {
             Some(TableWriter::PreInit(writer)) => {
-                let mut writer = FileWriter::new(writer, schema, None, Default::default());
-                writer.set_custom_schema_metadata(self.metadata.clone());
+                let schema = Arc::new(batch.schema().clone());
+                let ipc_fields = matches!(self.legacy, Some(LegacyTable::Read { .. }))
+                    .then(|| legacy::ipc_fields(&schema));
+                let mut writer = FileWriter::new(writer, schema, ipc_fields, Default::default());
+                let metadata = match &self.table_name {
+                    Some(name) => {
+                        let mut metadata = (*self.metadata).clone();
+                        metadata.insert(TABLE_NAME_KEY.into(), name.clone());
+                        Arc::new(metadata)
+                    }
+                    None => self.metadata.clone(),
+                };
+                writer.set_custom_schema_metadata(metadata);
                 writer.start()?;
                 writer
             }
@@ -448,17 +1429,44 @@ where
                 panic!("Writer missing; should not be possible")
             }
         };
-
-        w.write(&batch, None).unwrap();
+        let res = w.write(&batch, None);
         self.inner = Some(TableWriter::PostInit(w));
+        res?;
         Ok(())
     }
 
     pub fn finish(mut self) -> Result<(), WriteError> {
+        if let Some(mut pending) = self.pending.take() {
+            pending.rechunk_mut();
+            self.write_chunks(&pending)?;
+        }
+        if let Some(LegacyTable::Read {
+            version,
+            run_infos,
+            chunks,
+        }) = &mut self.legacy
+        {
+            let chunks = std::mem::take(chunks);
+            let (version, run_infos) = (*version, run_infos.take());
+            for batch in legacy::to_legacy_batches(&chunks, run_infos.as_ref(), version)? {
+                self.write_record_batch(batch)?;
+            }
+        }
+        if let Some(LegacyTable::RunInfo(run_infos)) = &mut self.legacy
+            && let Some(TableWriter::PreInit(writer)) = &mut self.inner
+        {
+            writer.contents_writtens.insert(ContentType::RunInfoTable);
+            writer.legacy_run_infos = run_infos.take();
+            return Ok(());
+        }
         if let Some(TableWriter::PostInit(mut x)) = self.inner.take() {
             x.finish()?;
             let inner = x.into_inner();
             inner.end_table(T::content_type().into_content_type())?;
+            if let (Some(read_ids), Some(index)) = (self.read_ids.take(), &mut inner.read_id_index)
+            {
+                index.extend(read_ids);
+            }
         }
         Ok(())
     }
@@ -470,7 +1478,7 @@ mod test {
 
     use polars::{
         df,
-        prelude::{CategoricalChunkedBuilder, CategoricalOrdering, CompatLevel},
+        prelude::{CategoricalChunkedBuilder, CategoricalOrdering, CompatLevel, NamedFrom},
         series::{IntoSeries, Series},
     };
     use polars_arrow::io::ipc::read::{FileReader, read_file_metadata};
@@ -478,6 +1486,7 @@ mod test {
     use super::*;
     use crate::{
         dataframe::{compatibility::record_batch_to_compat, get_next_df},
+        error::Pod5Error,
         reader::Reader,
     };
 
@@ -675,8 +1684,10 @@ mod test {
         // writer.write_dataframe(&read_df).unwrap();
 
         writer._finish().unwrap();
-        let mut inner = writer.writer;
+        let mut inner = std::mem::take(&mut writer.writer);
         inner.rewind().unwrap();
+        let report = pod5_format::validate(&mut inner).unwrap();
+        assert!(report.is_valid(), "{:?}", report.problems());
 
         let mut reader = Reader::from_reader(inner).unwrap();
         println!("AFTER: {:?}", reader.footer.footer());
@@ -700,6 +1711,484 @@ mod test {
         println!("read complete");
     }
 
+    #[test]
+    fn test_raw_signal_passthrough() -> eyre::Result<()> {
+        let path = "../extra/multi_fast5_zip_v3.pod5";
+        let mut reader = Reader::from_reader(File::open(path)?)?;
+        let raw = reader.signal_dfs()?.raw().collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(raw[0].0.column("signal")?.dtype(), &DataType::Binary);
+
+        let mut writer = Writer::from_writer(Cursor::new(Vec::new()))?;
+        writer.with_guard(|g| raw.iter().try_for_each(|df| g.write_batch(df)))?;
+        writer._finish()?;
+        let mut inner = std::mem::take(&mut writer.writer);
+        inner.rewind()?;
+        let mut written = Reader::from_reader(inner)?;
+
+        // Compressed signal is copied byte for byte
+        let copied = written.signal_dfs()?.raw().collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(copied, raw);
+        let decoded = written.signal_dfs()?.collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(
+            decoded,
+            reader.signal_dfs()?.collect::<Result<Vec<_>, _>>()?
+        );
+
+        let rows = [2, 0];
+        let taken = written.signal_table_reader()?.raw().take_rows(&rows)?;
+        let expected = reader.signal_table_reader()?.raw().take_rows(&rows)?;
+        assert_eq!(taken, expected);
+        assert_eq!(taken.0.column("signal")?.dtype(), &DataType::Binary);
+        Ok(())
+    }
+
+    #[test]
+    fn test_batch_size() -> eyre::Result<()> {
+        let mut reader = Reader::from_reader(File::open("../extra/multi_fast5_zip_v3.pod5")?)?;
+        let signal_df = reader.signal_dfs()?.next().unwrap()?;
+        let mut two_chunks = signal_df.0.clone();
+        two_chunks.vstack_mut(&signal_df.0)?;
+        let rows = two_chunks.height();
+
+        let heights = |mut writer: Writer<Cursor<Vec<u8>>>| -> eyre::Result<Vec<usize>> {
+            let mut inner = std::mem::take(&mut writer.writer);
+            inner.rewind()?;
+            let mut reader = Reader::from_reader(inner)?;
+            Ok(reader
+                .signal_dfs()?
+                .map(|df| df.map(|df| df.0.height()))
+                .collect::<Result<Vec<_>, _>>()?)
+        };
+
+        // Every chunk is written by default
+        let mut writer = Writer::from_writer(Cursor::new(Vec::new()))?;
+        writer.with_guard(|g| g.write_batch(&SignalDataFrame(two_chunks.clone())))?;
+        writer._finish()?;
+        assert_eq!(heights(writer)?, [signal_df.0.height(); 2]);
+
+        // Rows are buffered across writes and rechunked
+        let mut writer = Writer::from_writer(Cursor::new(Vec::new()))?.with_batch_size(3);
+        writer.with_guard::<SignalDataFrame, _>(|g| {
+            for offset in 0..rows {
+                g.write_batch(&SignalDataFrame(two_chunks.slice(offset as i64, 1)))?;
+            }
+            Ok(())
+        })?;
+        writer._finish()?;
+        let mut expected = vec![3; rows / 3];
+        if rows % 3 > 0 {
+            expected.push(rows % 3);
+        }
+        assert_eq!(heights(writer)?, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn test_schema_coercion() -> eyre::Result<()> {
+        let mut reader = Reader::from_reader(File::open("../extra/multi_fast5_zip_v3.pod5")?)?;
+        let read_df = reader.read_dfs()?.next().unwrap()?;
+        let mut coerced = read_df.0.clone();
+        coerced.apply("pore_type", |c| c.cast(&DataType::String).unwrap())?;
+        coerced.apply("num_samples", |c| c.cast(&DataType::UInt32).unwrap())?;
+        let coerced = coerced.select(
+            ["num_samples", "pore_type"].into_iter().chain(
+                read_df
+                    .0
+                    .get_column_names_str()
+                    .into_iter()
+                    .filter(|name| !["num_samples", "pore_type"].contains(name)),
+            ),
+        )?;
+
+        let mut writer = Writer::from_writer(Cursor::new(Vec::new()))?;
+        writer.with_guard(|g| g.write_batch(&ReadDataFrame(coerced.clone())))?;
+        writer._finish()?;
+        let mut inner = std::mem::take(&mut writer.writer);
+        inner.rewind()?;
+        let mut reader = Reader::from_reader(inner)?;
+        let read_back = reader.read_dfs()?.next().unwrap()?;
+        assert_eq!(read_back, read_df);
+
+        let signal = df!(
+            "read_id" => ["67e55044-10b1-426f-9247-bb680e5fe0c8"],
+            "signal" => [[100i16, 200i16].iter().collect::<Series>()],
+            "samples" => ["2"],
+        )?;
+        let mut writer = Writer::from_writer(Cursor::new(Vec::new()))?;
+        let mut guard = writer.guard::<SignalDataFrame>();
+        let err = guard
+            .write_batch(&SignalDataFrame(signal.clone()))
+            .unwrap_err();
+        assert!(
+            matches!(
+                &err,
+                WriteError::ColumnType { column, expected, actual }
+                    if column == "samples"
+                        && **expected == DataType::UInt32
+                        && **actual == DataType::String
+            ),
+            "{err}"
+        );
+
+        // Casts that could truncate or round values are rejected
+        for (samples, dtype) in [
+            (Series::new("samples".into(), [2.5f32]), DataType::Float32),
+            (Series::new("samples".into(), [2i64]), DataType::Int64),
+            (Series::new("samples".into(), [2i32]), DataType::Int32),
+        ] {
+            let mut signal = signal.clone();
+            signal.with_column(samples)?;
+            let err = guard.write_batch(&SignalDataFrame(signal)).unwrap_err();
+            assert!(
+                matches!(
+                    &err,
+                    WriteError::ColumnType { column, actual, .. }
+                        if column == "samples" && **actual == dtype
+                ),
+                "{err}"
+            );
+        }
+        let mut widened = signal.clone();
+        widened.with_column(Series::new("samples".into(), [2u16]))?;
+        guard.write_batch(&SignalDataFrame(widened))?;
+
+        let err = guard
+            .write_batch(&SignalDataFrame(signal.drop("samples")?))
+            .unwrap_err();
+        assert!(
+            matches!(&err, WriteError::MissingColumn { column, .. } if column == "samples"),
+            "{err}"
+        );
+        drop(guard);
+
+        // Timestamps more precise than the schema's milliseconds are rejected
+        let mut reader = Reader::from_reader(File::open("../extra/multi_fast5_zip_v3.pod5")?)?;
+        let mut run_info = reader.run_info_dfs()?.next().unwrap()?.0;
+        let DataType::Datetime(_, tz) = run_info.column("acquisition_start_time")?.dtype().clone()
+        else {
+            panic!("expected acquisition_start_time to be a Datetime");
+        };
+        let ns = DataType::Datetime(TimeUnit::Nanoseconds, tz);
+        run_info.apply("acquisition_start_time", |c| c.cast(&ns).unwrap())?;
+        let err = writer
+            .with_guard(|g| g.write_batch(&RunInfoDataFrame(run_info.clone())))
+            .unwrap_err();
+        assert!(
+            matches!(
+                &err,
+                WriteError::ColumnType { column, actual, .. }
+                    if column == "acquisition_start_time" && **actual == ns
+            ),
+            "{err}"
+        );
+        Ok(())
+    }
+
+    /// A copy of `source` in a new directory, for appending to.
+    fn append_copy(source: &str) -> eyre::Result<(PathBuf, PathBuf)> {
+        let dir = std::env::temp_dir().join(format!("pod5_polars_test_{}", Uuid::new_v4()));
+        std::fs::create_dir(&dir)?;
+        let path = dir.join("reads.pod5");
+        std::fs::copy(source, &path)?;
+        Ok((dir, path))
+    }
+
+    #[test]
+    fn test_open_append() -> eyre::Result<()> {
+        let (dir, path) = append_copy("../extra/multi_fast5_zip_v3.pod5")?;
+        let original = ParsedFooter::read_footer(File::open(&path)?)?.info()?;
+        let labels = df!(
+            "read_id" => ["0000173c-bf67-44e7-9a9c-1ad0bc728e74"],
+            "label" => ["pass"],
+        )?;
+
+        // A failed append leaves the original file
+        let mut writer = Writer::open_append(&path)?;
+        let mut guard = writer.guard::<OtherDataFrame>();
+        guard.write_batch(&OtherDataFrame::new("labels", labels.clone()))?;
+        drop(guard);
+        writer.abort()?;
+        let footer = ParsedFooter::read_footer(File::open(&path)?)?.info()?;
+        assert_eq!(footer.tables(), original.tables());
+        assert!(pod5_format::validate(File::open(&path)?)?.is_valid());
+        assert_eq!(std::fs::read_dir(&dir)?.count(), 1);
+
+        let mut writer = Writer::open_append(&path)?;
+        // New tables are staged next to the file
+        assert_eq!(std::fs::read_dir(&dir)?.count(), 2);
+        writer.with_guard(|g| g.write_batch(&OtherDataFrame::new("labels", labels.clone())))?;
+        let signal_df = SignalDataFrame::default();
+        assert!(matches!(
+            writer.with_guard(|g| g.write_batch(&signal_df)),
+            Err(WriteError::ContentTypeAlreadyWritten(
+                ContentType::SignalTable
+            ))
+        ));
+        writer.finish()?;
+
+        let report = pod5_format::validate(File::open(&path)?)?;
+        assert!(report.is_valid(), "{:?}", report.problems());
+        let footer = ParsedFooter::read_footer(File::open(&path)?)?.info()?;
+        assert_eq!(footer.file_identifier(), original.file_identifier());
+        assert_eq!(footer.tables()[..3], *original.tables());
+        assert_eq!(footer.tables().len(), 4);
+        assert_eq!(footer.software(), original.software());
+        assert_eq!(footer.pod5_version(), original.pod5_version());
+        assert_eq!(std::fs::read_dir(&dir)?.count(), 1);
+
+        let mut reader = Reader::from_reader(File::open(&path)?)?;
+        let read_back = reader.other_dfs("labels")?.collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(read_back, [labels]);
+        let mut expected = Reader::from_reader(File::open("../extra/multi_fast5_zip_v3.pod5")?)?;
+        assert_eq!(
+            reader.reads()?.collect::<Result<Vec<_>, _>>()?,
+            expected.reads()?.collect::<Result<Vec<_>, _>>()?
+        );
+        std::fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_open_append_restore() -> eyre::Result<()> {
+        let (dir, path) = append_copy("../extra/multi_fast5_zip_v3.pod5")?;
+        let original = std::fs::read(&path)?;
+        let labels = df!("label" => ["pass"])?;
+
+        let mut writer = Writer::open_append(&path)?;
+        writer.with_guard(|g| g.write_batch(&OtherDataFrame::new("labels", labels.clone())))?;
+        writer._finish()?;
+        writer.flush()?;
+        let mut target = writer.append.take().unwrap();
+        // Fail part way through replacing the footer
+        target.file.seek(SeekFrom::Start(target.start))?;
+        target.file.write_all(&[0; 64])?;
+        target.restore()?;
+        assert_eq!(std::fs::read(&path)?, original);
+        assert!(target.temp.exists());
+        std::fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_open_append_schema_version() -> eyre::Result<()> {
+        let (dir, path) = append_copy("../extra/multi_fast5_zip_v1.pod5")?;
+        let original = ParsedFooter::read_footer(File::open(&path)?)?.info()?;
+
+        let writer = Writer::open_append(&path)?;
+        assert_eq!(writer.schema_version, SchemaVersion::V1);
+        writer.finish()?;
+        let footer = ParsedFooter::read_footer(File::open(&path)?)?.info()?;
+        assert_eq!(footer.software(), original.software());
+        assert_eq!(footer.pod5_version(), original.pod5_version());
+
+        let options = WriterOptions::default().with_software("relabel");
+        let writer = Writer::open_append_with_options(&path, options)?;
+        assert_eq!(writer.schema_version, SchemaVersion::V3);
+        writer.finish()?;
+        let footer = ParsedFooter::read_footer(File::open(&path)?)?.info()?;
+        assert_eq!(footer.software(), Some("relabel"));
+        assert_eq!(footer.file_identifier(), original.file_identifier());
+        std::fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_open_append_killed() -> eyre::Result<()> {
+        let (dir, path) = append_copy("../extra/multi_fast5_zip_v3.pod5")?;
+        let original = std::fs::read(&path)?;
+        let labels = df!("label" => ["pass"])?;
+
+        // Nothing runs when the process dies part way through a table
+        let mut writer = Writer::open_append(&path)?;
+        let mut guard = writer.guard::<OtherDataFrame>();
+        guard.write_batch(&OtherDataFrame::new("labels", labels.clone()))?;
+        std::mem::forget(guard);
+        std::mem::forget(writer);
+        assert_eq!(std::fs::read(&path)?, original);
+        let mut reader = Reader::from_reader(File::open(&path)?)?;
+        assert_eq!(reader.reads()?.count(), 10);
+        std::fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_create_atomic() -> eyre::Result<()> {
+        let dir =
+            std::env::temp_dir().join(format!("pod5_polars_test_create_atomic_{}", Uuid::new_v4()));
+        std::fs::create_dir(&dir)?;
+        let path = dir.join("out.pod5");
+        let mut reader = Reader::from_reader(File::open("../extra/multi_fast5_zip_v3.pod5")?)?;
+        let run_info_df = reader.run_info_dfs()?.next().unwrap()?;
+        let files = || -> eyre::Result<usize> { Ok(std::fs::read_dir(&dir)?.count()) };
+
+        // Dropping an unfinished Writer removes the temporary file
+        let mut writer = Writer::create_atomic(&path)?;
+        writer.with_guard(|g| g.write_batch(&run_info_df))?;
+        assert_eq!(files()?, 1);
+        assert!(!path.exists());
+        drop(writer);
+        assert_eq!(files()?, 0);
+
+        let mut writer = Writer::create_atomic(&path)?;
+        writer.with_guard(|g| g.write_batch(&run_info_df))?;
+        assert!(!path.exists());
+        writer.finish()?;
+        assert_eq!(files()?, 1);
+        let report = pod5_format::validate(File::open(&path)?)?;
+        assert!(report.is_valid(), "{:?}", report.problems());
+
+        std::fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_finalize_on_drop() -> eyre::Result<()> {
+        let mut reader = Reader::from_reader(File::open("../extra/multi_fast5_zip_v3.pod5")?)?;
+        let run_info_df = reader.run_info_dfs()?.next().unwrap()?;
+        let signal_df = reader.signal_dfs()?.next().unwrap()?;
+
+        let mut buf = Cursor::new(Vec::new());
+        let mut writer = Writer::from_writer(&mut buf)?.finalize_on_drop();
+        writer.with_guard(|g| g.write_batch(&run_info_df))?;
+        // Left out of the footer since the table isn't finished
+        let mut guard = writer.guard::<SignalDataFrame>();
+        guard.write_batch(&signal_df)?;
+        drop(guard);
+        drop(writer);
+
+        buf.rewind()?;
+        let report = pod5_format::validate(&mut buf)?;
+        assert!(report.is_valid(), "{:?}", report.problems());
+        let mut reader = Reader::from_reader(buf)?;
+        let content_types = reader
+            .tables()?
+            .iter()
+            .map(|t| t.content_type())
+            .collect::<Vec<_>>();
+        assert_eq!(content_types, [ContentType::RunInfoTable]);
+        assert_eq!(reader.run_info_dfs()?.next().unwrap()?, run_info_df);
+        Ok(())
+    }
+
+    #[test]
+    fn test_end_table_write_error() -> eyre::Result<()> {
+        let mut reader = Reader::from_reader(File::open("../extra/multi_fast5_zip_v3.pod5")?)?;
+        let run_info_df = reader.run_info_dfs()?.next().unwrap()?;
+        let mut writer = Writer::from_writer(Cursor::new(Vec::new()))?;
+        writer.with_guard(|g| g.write_batch(&run_info_df))?;
+        let table = &writer.tables[0];
+        let table_end = (table.offset() + table.length()) as usize;
+
+        // Room for the table but not the section marker after it
+        let mut buf = vec![0u8; table_end + 4];
+        let mut writer = Writer::from_writer(Cursor::new(buf.as_mut_slice()))?.finalize_on_drop();
+        let err = writer
+            .with_guard(|g| g.write_batch(&run_info_df))
+            .unwrap_err();
+        assert!(
+            matches!(err, WriteError::FailedToWriteSectionMarker(_)),
+            "{err}"
+        );
+        // Finishing on drop fails the same way without panicking
+        drop(writer);
+        Ok(())
+    }
+
+    #[test]
+    fn test_reproducible_output() -> eyre::Result<()> {
+        let mut reader = Reader::from_reader(File::open("../extra/multi_fast5_zip_v3.pod5")?)?;
+        let run_info_df = reader.run_info_dfs()?.next().unwrap()?;
+        let read_df = reader.read_dfs()?.next().unwrap()?;
+        let write = |options: WriterOptions| -> eyre::Result<Vec<u8>> {
+            let mut buf = Cursor::new(Vec::new());
+            let mut writer = Writer::from_writer_with_options(&mut buf, options)?;
+            writer.with_guard(|g| g.write_batch(&run_info_df))?;
+            writer.with_guard(|g| g.write_batch(&read_df))?;
+            writer.finish()?;
+            Ok(buf.into_inner())
+        };
+
+        assert_eq!(
+            write(WriterOptions::from_seed(7))?,
+            write(WriterOptions::from_seed(7))?
+        );
+        assert_ne!(
+            write(WriterOptions::from_seed(7))?,
+            write(WriterOptions::from_seed(8))?
+        );
+
+        let file_identifier = Uuid::parse_str("67e55044-10b1-426f-9247-bb680e5fe0c8")?;
+        let options = WriterOptions::new()
+            .with_file_identifier(file_identifier)
+            .with_section_marker(Uuid::nil())
+            .with_software("test-writer")
+            .with_pod5_version("0.3.2");
+        let data = write(options.clone())?;
+        assert_eq!(data, write(options)?);
+        assert_eq!(&data[8..24], Uuid::nil().as_bytes());
+        let footer = ParsedFooter::from_bytes(&data)?.info()?;
+        assert_eq!(
+            footer.file_identifier(),
+            Some(file_identifier.to_string().as_str())
+        );
+        assert_eq!(footer.software(), Some("test-writer"));
+        assert_eq!(footer.pod5_version(), Some("0.3.2"));
+        let report = pod5_format::validate(Cursor::new(&data))?;
+        assert!(report.is_valid(), "{:?}", report.problems());
+        Ok(())
+    }
+
+    #[test]
+    fn test_other_tables() -> eyre::Result<()> {
+        let labels = df!(
+            "read_id" => ["0000173c-bf67-44e7-9a9c-1ad0bc728e74", "67e55044-10b1-426f-9247-bb680e5fe0c8"],
+            "label" => ["pass", "fail"],
+            "score" => [0.9f32, 0.1f32],
+        )?;
+        let summary = df!("reads" => [2u64])?;
+
+        let mut writer = Writer::from_writer(Cursor::new(Vec::new()))?;
+        let labels_df = OtherDataFrame::new("labels", labels.clone());
+        writer.with_guard(|g| g.write_batch(&labels_df))?;
+        let summary_df = OtherDataFrame::new("summary", summary.clone());
+        writer.with_guard(|g| g.write_batch(&summary_df))?;
+        writer._finish()?;
+        let mut inner = std::mem::take(&mut writer.writer);
+        inner.rewind()?;
+
+        let mut reader = Reader::from_reader(inner)?;
+        assert_eq!(reader.tables()?.len(), 2);
+        assert_eq!(reader.other_table_names()?, ["labels", "summary"]);
+        let read_back = reader.other_dfs("labels")?.collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(read_back, [labels]);
+        assert!(matches!(
+            reader.other_dfs("missing"),
+            Err(Pod5Error::OtherTableMissing(_))
+        ));
+
+        // Copy every user table into a new file
+        let mut copy = Writer::from_writer(Cursor::new(Vec::new()))?;
+        for table in reader.tables()? {
+            let mut iter = reader.open_table(&table)?;
+            let name = iter.name().unwrap_or_default().to_string();
+            let dfs = iter.by_ref().collect::<Result<Vec<_>, _>>()?;
+            copy.with_guard::<OtherDataFrame, _>(|g| {
+                dfs.iter().try_for_each(|df| {
+                    g.write_batch(&OtherDataFrame::new(name.clone(), df.clone()))
+                })
+            })?;
+        }
+        copy._finish()?;
+        let mut inner = std::mem::take(&mut copy.writer);
+        inner.rewind()?;
+        let mut reader = Reader::from_reader(inner)?;
+        let read_back = reader
+            .other_dfs("summary")?
+            .collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(read_back, [summary]);
+        Ok(())
+    }
+
     #[test]
     fn test_writer_signal_df() {
         let minknow_uuid = [
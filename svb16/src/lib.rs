@@ -64,14 +64,25 @@ impl Iterator for DecodeIter<'_> {
 }
 
 /// zstd -> streamvbyte -> zig-zag -> delta
-/// Can panic if the compressed array doesn't follow the SVB16 specification.
+/// Returns an `InvalidData` error if the decompressed array is too short to
+/// hold `count` values.
 ///
 /// When running on compressed signal data from a signal column in a POD5 file,
 /// use `decode` on the individual rows. If you try to combine the compressed
 /// signal across multiple rows that correspond to a signal read this function
-/// will panic.
+/// will fail.
 pub fn decode(compressed: &[u8], count: usize) -> io::Result<Vec<i16>> {
     let compressed = zstd::decode_all(compressed)?;
+    let ctrl_len = num_ctrl_bytes(count);
+    let data_len = compressed
+        .get(..ctrl_len)
+        .map(|ctrl| count + ctrl.view_bits::<Lsb0>()[..count].count_ones());
+    if data_len.is_none_or(|data_len| compressed.len() - ctrl_len < data_len) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("compressed signal too short for {count} samples"),
+        ));
+    }
     Ok(DecodeIter::from_compressed(&compressed, count)
         .map(ZigZag::decode)
         .original()
@@ -200,6 +211,15 @@ mod test {
         assert_eq!(decode(&encode(&nums).unwrap(), nums.len()).unwrap(), nums);
     }
 
+    #[test]
+    fn test_decode_invalid() {
+        let nums = [10i16, 1234, 20, 2345, 30];
+        let compressed = encode(&nums).unwrap();
+        assert!(decode(&compressed, nums.len() + 1).is_err());
+        assert!(decode(&compressed, 100).is_err());
+        assert!(decode(&compressed[..compressed.len() / 2], nums.len()).is_err());
+    }
+
     proptest! {
         #[test]
         fn proptest_round_trip(ref vec in any::<Vec<i16>>()) {